@@ -0,0 +1,42 @@
+#![feature(test)]
+
+extern crate test;
+
+use mines_rs::{GameServer, GameSpec, NativeServer};
+use test::Bencher;
+
+fn spec(dims: &[usize], mines: usize) -> GameSpec {
+    GameSpec {
+        dims: dims.to_vec(),
+        mines,
+        seed: 133337,
+        autoclear: true,
+        rng: Default::default(),
+    }
+}
+
+#[bench]
+fn bench_new_game(b: &mut Bencher) {
+    b.iter(|| NativeServer::new(spec(&[100, 100], 2000), false).unwrap());
+}
+
+// A sparse board, so the first clear opens most of it
+#[bench]
+fn bench_large_opening(b: &mut Bencher) {
+    b.iter(|| {
+        let mut server = NativeServer::new(spec(&[300, 300], 50), false).unwrap();
+        let start = server.start_coords();
+
+        server.turn(vec![start], vec![], vec![]).unwrap()
+    });
+}
+
+#[bench]
+fn bench_large_opening_3d(b: &mut Bencher) {
+    b.iter(|| {
+        let mut server = NativeServer::new(spec(&[40, 40, 40], 50), false).unwrap();
+        let start = server.start_coords();
+
+        server.turn(vec![start], vec![], vec![]).unwrap()
+    });
+}
@@ -0,0 +1,155 @@
+use serde_derive::{Deserialize, Serialize};
+
+use crate::coords::Coords;
+use crate::server::native::NativeServer;
+
+/// Difficulty measures intrinsic to a board's mine layout, independent of how it is played. Can be
+/// returned from the `use_game_result` callback of `GameBatch::run` to break batch results down
+/// by board difficulty.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BoardMetrics {
+    /// Minimum number of clear requests needed to win the board, assuming autoclear.
+    pub three_bv: usize,
+    /// Number of connected regions of empty cells with no surrounding mines.
+    pub openings: usize,
+    /// Number of numbered cells which don't border an opening, so must each be cleared
+    /// individually.
+    pub isolated: usize,
+    /// Mine density of cells grouped by how many dimensions they lie on the board's edge, from
+    /// interior cells (0) up to corners (one per dimension).
+    pub region_density: Vec<RegionDensity>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RegionDensity {
+    pub edge_dims: usize,
+    pub cells: usize,
+    pub mines: usize,
+}
+
+impl RegionDensity {
+    pub fn density(&self) -> f64 {
+        if self.cells == 0 {
+            0.0
+        } else {
+            self.mines as f64 / self.cells as f64
+        }
+    }
+}
+
+impl BoardMetrics {
+    pub fn new(server: &NativeServer) -> Self {
+        let size = server.dims.iter().product();
+
+        // Cells which are cleared as part of some opening, either as an empty cell or a numbered
+        // cell on its border.
+        let mut in_opening = vec![false; size];
+        let mut openings = 0;
+        let mut to_visit = Vec::new();
+
+        for index in 0..size {
+            let cell = server.cell(index);
+
+            if cell.mine || cell.surr_mine_count != 0 || in_opening[index] {
+                continue;
+            }
+
+            openings += 1;
+            in_opening[index] = true;
+            to_visit.push(index);
+
+            while let Some(i) = to_visit.pop() {
+                for surr in server.neighbours(i).iter() {
+                    if in_opening[surr] {
+                        continue;
+                    }
+
+                    in_opening[surr] = true;
+
+                    if server.cell(surr).surr_mine_count == 0 {
+                        to_visit.push(surr);
+                    }
+                }
+            }
+        }
+
+        let isolated = server
+            .cells()
+            .zip(in_opening.iter())
+            .filter(|&(cell, &in_opening)| !cell.mine && !in_opening)
+            .count();
+
+        let mut region_density: Vec<RegionDensity> = (0..=server.dims.len())
+            .map(|edge_dims| RegionDensity {
+                edge_dims,
+                cells: 0,
+                mines: 0,
+            })
+            .collect();
+
+        for (index, cell) in server.cells().enumerate() {
+            let Coords(coords): Coords = Coords::from_index(index, &server.dims);
+            let edge_dims = coords
+                .iter()
+                .zip(server.dims.iter())
+                .filter(|&(&c, &d)| c == 0 || c == d - 1)
+                .count();
+            let region = &mut region_density[edge_dims];
+
+            region.cells += 1;
+
+            if cell.mine {
+                region.mines += 1;
+            }
+        }
+
+        BoardMetrics {
+            three_bv: openings + isolated,
+            openings,
+            isolated,
+            region_density,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::analysis::BoardMetrics;
+    use crate::server::native::{Layout, NativeServer};
+
+    fn metrics(layout: &str) -> BoardMetrics {
+        let layout = Layout::parse(layout).unwrap();
+        BoardMetrics::new(&NativeServer::from_layout(&layout, true, false).unwrap())
+    }
+
+    #[test]
+    fn test_board_metrics() {
+        // A single opening reaching every numbered cell
+        let single = metrics("*....\n.....\n....*");
+        assert_eq!(
+            (single.three_bv, single.openings, single.isolated),
+            (1, 1, 0)
+        );
+
+        let regions: Vec<(usize, usize, usize)> = single
+            .region_density
+            .iter()
+            .map(|r| (r.edge_dims, r.cells, r.mines))
+            .collect();
+        assert_eq!(regions, vec![(0, 3, 0), (1, 8, 0), (2, 4, 2)]);
+
+        // A wall of mines between two openings
+        let walled = metrics("..*....\n..*....\n..*....");
+        assert_eq!(
+            (walled.three_bv, walled.openings, walled.isolated),
+            (2, 2, 0)
+        );
+
+        // No empty cells, so every safe cell is cleared on its own
+        let crowded = metrics("...*...\n***.***\n...*...");
+        assert_eq!(
+            (crowded.three_bv, crowded.openings, crowded.isolated),
+            (13, 0, 13)
+        );
+    }
+}
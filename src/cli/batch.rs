@@ -0,0 +1,388 @@
+use chrono::Utc;
+use mines_rs::{
+    mongodb_connector, BatchConfig, BatchProgress, CancelToken, GameBatch, GameError, GameServer,
+    GameSpec, JsServerWrapper, MinesSpec, NativeServer, PairedResult, Precision, Shard, ShardBy,
+    ShardResult, SpecResult, Variant,
+};
+use std::fs::File;
+use std::io::{stderr, Write};
+use std::path::{Path, PathBuf};
+use std::process::exit;
+
+use crate::options::{
+    BatchOptions, BatchSpecOptions, CompareOptions, MergeOptions, ServerType, ShardOpt,
+};
+
+// Overwrites a single line on stderr, so doesn't get mixed up with the results on stdout
+fn print_progress(progress: BatchProgress) {
+    let BatchProgress {
+        spec_index,
+        spec_played,
+        spec_total,
+        played,
+        total,
+    } = progress;
+
+    if played % (total / 100).max(1) == 0 || played == total {
+        eprint!(
+            "\rSpec {}: {}/{} games, batch: {}/{} games",
+            spec_index + 1,
+            spec_played,
+            spec_total,
+            played,
+            total
+        );
+        stderr().flush().unwrap();
+    }
+}
+
+// A token cancelled by Ctrl-C, so that a batch stops and still prints the results so far. A
+// second Ctrl-C exits straight away.
+fn cancel_on_interrupt() -> Result<CancelToken, GameError> {
+    let cancel = CancelToken::new();
+    let handler_cancel = cancel.clone();
+
+    ctrlc::set_handler(move || {
+        if handler_cancel.is_cancelled() {
+            exit(130);
+        }
+
+        handler_cancel.cancel();
+    })?;
+
+    Ok(cancel)
+}
+
+fn print_cancelled() {
+    eprint!("\r{:60}\r", "");
+    eprintln!("Cancelled; results are for the games played so far");
+}
+
+enum RunMode<'a> {
+    Streaming,
+    Checkpointed(&'a Path),
+    /// The shard's results are written to stdout as JSON, to be merged with `merge` once every
+    /// shard is finished.
+    Shard(Shard),
+    Adaptive(Precision),
+}
+
+fn run_batch<G: GameServer>(
+    batch: BatchConfig,
+    mode: RunMode,
+    new_game: impl Fn(GameSpec) -> Result<G, GameError> + Sync,
+    use_game_result: impl Fn(G) + Sync,
+    cancel: &CancelToken,
+    on_spec: impl FnMut(SpecResult<()>),
+) -> Result<(), GameError> {
+    match mode {
+        RunMode::Shard(shard) => {
+            let shard_result = batch.run_shard(
+                shard,
+                new_game,
+                use_game_result,
+                cancel,
+                |_game, progress| print_progress(progress),
+            )?;
+
+            // A partial shard would be merged as if it were whole
+            if cancel.is_cancelled() {
+                Err("Cancelled; shard results are only written for the whole shard")?;
+            }
+
+            eprint!("\r{:60}\r", "");
+            println!("{}", serde_json::to_string(&shard_result)?);
+
+            Ok(())
+        }
+        RunMode::Checkpointed(path) => batch.run_checkpointed(
+            path,
+            new_game,
+            use_game_result,
+            cancel,
+            |_game, progress| print_progress(progress),
+            on_spec,
+        ),
+        RunMode::Adaptive(precision) => batch.run_adaptive(
+            precision,
+            new_game,
+            use_game_result,
+            cancel,
+            |_game, progress| print_progress(progress),
+            on_spec,
+        ),
+        RunMode::Streaming => batch.run_streaming(
+            new_game,
+            use_game_result,
+            cancel,
+            |_game, progress| print_progress(progress),
+            on_spec,
+        ),
+    }
+}
+
+// Plays every game under both variants, printing the paired results for each spec
+fn compare_variants<G: GameServer>(
+    batch: BatchConfig,
+    a: &Variant,
+    b: &Variant,
+    new_game: impl Fn(GameSpec) -> Result<G, GameError> + Sync,
+) -> Result<(), GameError> {
+    let cancel = cancel_on_interrupt()?;
+
+    println!("A: {}\nB: {}", a.name, b.name);
+    println!("Dims\t\tMines\tA wins\t\tB wins\t\tA only\tB only\tp");
+
+    batch.run_paired(a, b, new_game, &cancel, print_progress, |paired_result| {
+        let PairedResult {
+            dims,
+            mines,
+            played,
+            a_wins,
+            b_wins,
+            a_only,
+            b_only,
+            p_value,
+            ..
+        } = paired_result;
+        let perc = |wins| wins as f64 * 100f64 / played as f64;
+
+        eprint!("\r{:60}\r", "");
+        println!(
+            "{:?}\t{}:\t{} ({:.0}%)\t{} ({:.0}%)\t{}\t{}\t{:.3}",
+            dims,
+            mines,
+            a_wins,
+            perc(a_wins),
+            b_wins,
+            perc(b_wins),
+            a_only.len(),
+            b_only.len(),
+            p_value
+        );
+    })?;
+
+    if cancel.is_cancelled() {
+        print_cancelled();
+    }
+
+    Ok(())
+}
+
+fn merge_shards(paths: &[PathBuf]) -> Result<Vec<SpecResult<()>>, GameError> {
+    let mut shards = Vec::new();
+
+    for path in paths {
+        shards.push(serde_json::from_reader(File::open(path)?)?);
+    }
+
+    ShardResult::merge(shards)
+}
+
+// Prints a spec's result as soon as it's finished
+fn print_spec(spec_result: SpecResult<()>, print_lost_seeds: bool) {
+    let SpecResult {
+        name,
+        dims,
+        mines_spec,
+        mines,
+        wins,
+        played,
+        lost_seeds,
+        stats,
+        info: _,
+    } = spec_result;
+    let win_perc = wins as f64 * 100f64 / played as f64;
+    let mines = match mines_spec {
+        Some(density @ MinesSpec::Density(_)) => format!("{} ({})", mines, density),
+        _ => mines.to_string(),
+    };
+    let (ci_low, ci_high) = stats.win_rate_ci;
+    let time = match stats.time {
+        Some(time) => format!("{:.2}/{:.2}", time.mean * 1000.0, time.p90 * 1000.0),
+        None => String::from("-"),
+    };
+
+    // Clear the progress line first
+    eprint!("\r{:60}\r", "");
+
+    if let Some(name) = name {
+        print!("{}: ", name);
+    }
+
+    println!(
+        "{:?}\t{}:\t{}/{}\t({:.0}%)\t{:.0}-{:.0}%\t\t{:.1}/{}\t\t{}",
+        dims,
+        mines,
+        wins,
+        played,
+        win_perc,
+        ci_low * 100.0,
+        ci_high * 100.0,
+        stats.turns.mean,
+        stats.turns.p90,
+        time
+    );
+
+    if print_lost_seeds {
+        println!("Lost seeds: {:?}", lost_seeds);
+    }
+}
+
+fn print_header() {
+    println!("Dims\t\tMines\tWins/Played\t\t95% CI\t\tTurns (mean/p90)\tms/game (mean/p90)");
+}
+
+// A config file replaces the rest of the options
+fn batch_config(options: BatchSpecOptions) -> Result<BatchConfig, GameError> {
+    let BatchSpecOptions {
+        count_per_spec,
+        dims_range,
+        mines_range,
+        metaseed,
+        rng,
+        config,
+    } = options;
+
+    match config {
+        Some(path) => BatchConfig::load(&path),
+        None => Ok(GameBatch {
+            count_per_spec,
+            dims_range,
+            mines_range: mines_range.0,
+            autoclear: true,
+            metaseed,
+            rng,
+        }
+        .into_config()),
+    }
+}
+
+pub fn batch(options: BatchOptions) -> Result<(), GameError> {
+    let BatchOptions {
+        spec,
+        server_type,
+        save_to_db,
+        checkpoint,
+        lost_seeds: print_lost_seeds,
+        shard,
+        shard_by_spec,
+        ci_width,
+        round_size,
+    } = options;
+    let batch = batch_config(spec)?;
+
+    let shard = shard.map(|ShardOpt { index, count }| Shard {
+        index,
+        count,
+        by: if shard_by_spec {
+            ShardBy::Spec
+        } else {
+            ShardBy::Game
+        },
+    });
+
+    let precision = ci_width.map(|ci_width| Precision {
+        ci_width,
+        round_size,
+    });
+
+    let mode = match (checkpoint.as_ref().map(PathBuf::as_path), shard, precision) {
+        (None, None, None) => RunMode::Streaming,
+        (Some(path), None, None) => RunMode::Checkpointed(path),
+        (None, Some(shard), None) => RunMode::Shard(shard),
+        (None, None, Some(precision)) => RunMode::Adaptive(precision),
+        _ => Err("Only one of the -k, -S and -w options can be used at once")?,
+    };
+    let sharded = match mode {
+        RunMode::Shard(_) => true,
+        _ => false,
+    };
+
+    let cancel = cancel_on_interrupt()?;
+    let start = Utc::now();
+    let mut game_count = 0;
+
+    if !sharded {
+        print_header();
+    }
+
+    let on_spec = |spec_result: SpecResult<()>| {
+        game_count += spec_result.played;
+        print_spec(spec_result, print_lost_seeds);
+    };
+
+    match (server_type, save_to_db) {
+        (ServerType::Native, false) => run_batch(
+            batch,
+            mode,
+            |spec| NativeServer::new(spec, false),
+            |_game| (),
+            &cancel,
+            on_spec,
+        )?,
+        (ServerType::Native, true) => run_batch(
+            batch,
+            mode,
+            |spec| NativeServer::new(spec, true),
+            |game| {
+                mongodb_connector::insert_game(&game).unwrap();
+            },
+            &cancel,
+            on_spec,
+        )?,
+        (ServerType::Js, false) => run_batch(
+            batch,
+            mode,
+            JsServerWrapper::new,
+            |_game| (),
+            &cancel,
+            on_spec,
+        )?,
+        (ServerType::Js, true) => Err("The -b option is invalid for the JS server")?,
+    }
+
+    if cancel.is_cancelled() {
+        print_cancelled();
+    }
+
+    if sharded || game_count == 0 {
+        return Ok(());
+    }
+
+    let stop = Utc::now();
+    let dur_us = (stop - start).num_microseconds().unwrap();
+    let dur_s = dur_us as f64 / 1_000_000_f64;
+    let avg_us = dur_us / game_count as i64;
+
+    println!("Time: {:.2}s (avg {}µs/game/core)", dur_s, avg_us);
+
+    Ok(())
+}
+
+pub fn compare(options: CompareOptions) -> Result<(), GameError> {
+    let CompareOptions {
+        spec,
+        server_type,
+        a,
+        b,
+    } = options;
+    let batch = batch_config(spec)?;
+
+    match server_type {
+        ServerType::Native => {
+            compare_variants(batch, &a, &b, |spec| NativeServer::new(spec, false))
+        }
+        ServerType::Js => compare_variants(batch, &a, &b, JsServerWrapper::new),
+    }
+}
+
+pub fn merge(options: MergeOptions) -> Result<(), GameError> {
+    print_header();
+
+    for spec_result in merge_shards(&options.shards)? {
+        print_spec(spec_result, options.lost_seeds);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,351 @@
+use mines_rs::{
+    AnsiRenderer, ApiHost, BoardMetrics, Client, Coords, GameError, GameState, Layout,
+    NativeServer, Replay, SolverOverlay, TextRenderer, Variant,
+};
+use std::io::{stdin, stdout, BufRead, Write};
+
+use crate::options::{GameOptions, PlayOptions, RenderOptions, ServeOptions, SolveOptions};
+
+fn new_game(game: &GameOptions, variant: &Variant) -> Result<NativeServer, GameError> {
+    let mut spec = game.spec()?;
+    spec.autoclear = variant.autoclear.unwrap_or(spec.autoclear);
+
+    NativeServer::new(spec, true)
+}
+
+fn print_metrics(server: &NativeServer) {
+    let BoardMetrics {
+        three_bv,
+        openings,
+        isolated,
+        region_density,
+    } = BoardMetrics::new(server);
+
+    println!(
+        "3BV: {}, openings: {}, isolated cells: {}",
+        three_bv, openings, isolated
+    );
+
+    for region in region_density {
+        println!(
+            "Cells on {} edges: {} mines/{} ({:.1}%)",
+            region.edge_dims,
+            region.mines,
+            region.cells,
+            region.density() * 100.0
+        );
+    }
+}
+
+// Prints the replay's turns, with what the solver had deduced going into each
+fn print_replay(replay: &Replay) {
+    let Replay { server, solver } = replay;
+    let turns = server.turns.as_ref().unwrap();
+
+    // The first stored turn is the initial state
+    for (i, (step, turn)) in solver.iter().zip(turns.iter().skip(1)).enumerate() {
+        println!(
+            "Turn {}: {} {:?}, cleared {}, flagged {}, deduced {} safe/{} mines, {} cells left",
+            i + 1,
+            if step.guess { "guessed" } else { "cleared" },
+            turn.clear_req,
+            turn.clear_actual.len(),
+            turn.flagged.len(),
+            step.overlay.safe.len(),
+            step.overlay.mines.len(),
+            turn.cells_rem
+        );
+    }
+
+    println!(
+        "{:?}\n{}",
+        server.game_state,
+        TextRenderer::default().render(server)
+    );
+}
+
+const INTERACTIVE_HELP: &str = "\
+Commands:
+  c X,Y    Clear a cell
+  f X,Y    Flag a cell
+  u X,Y    Unflag a cell
+  h        Hint: show a cell which is provably safe
+  s Z[,..] Show the slice at these co-ordinates of the dimensions after the second
+  n, p     Show the next or previous slice
+  ?        Show this help
+  q        Quit
+Co-ordinates of dimensions after the second can be left out, for the slice on show.";
+
+// Prints a 2D slice of the board, with the co-ordinates of its columns and rows
+fn print_slice(server: &NativeServer, overlay: Option<&SolverOverlay>, slice: &[usize]) {
+    let dims = &server.dims;
+    let label_width = (dims.get(1).cloned().unwrap_or(1) - 1).to_string().len();
+    let board = AnsiRenderer::default().render_slice(server, overlay, slice);
+
+    if !slice.is_empty() {
+        println!("Slice {:?} of {:?}", slice, &dims[2..]);
+    }
+
+    // Only the last digit fits above each column
+    let columns: Vec<String> = (0..dims[0]).map(|x| (x % 10).to_string()).collect();
+    println!("{:w$} {}", "", columns.join(" "), w = label_width);
+
+    for (y, row) in board.lines().enumerate() {
+        println!("{:>w$} {}", y, row, w = label_width);
+    }
+}
+
+// Moves along the slices of the dimensions after the second by `step`, wrapping around
+fn step_slice(dims: &[usize], slice: &[usize], step: isize) -> Vec<usize> {
+    let slice_dims = &dims[dims.len() - slice.len()..];
+    let count = slice_dims.iter().product::<usize>() as isize;
+    let index = Coords(slice.to_vec()).to_index(slice_dims) as isize;
+
+    Coords::from_index(
+        ((index + step) % count + count) as usize % count as usize,
+        slice_dims,
+    )
+    .0
+}
+
+// Parses co-ordinates such as `3,4`, filling in the current slice for any left out
+fn parse_coords(arg: &str, slice: &[usize], dims: &[usize]) -> Result<Coords, GameError> {
+    let mut coords: Vec<usize> = arg
+        .split(',')
+        .map(|c| c.trim().parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| {
+            format!(
+                "Co-ordinates should be numbers separated by commas, not `{}`",
+                arg
+            )
+        })?;
+
+    if coords.len() == dims.len() - slice.len() {
+        coords.extend(slice);
+    }
+
+    let coords = Coords(coords);
+    coords.try_to_index(dims)?;
+
+    Ok(coords)
+}
+
+// Lets a human play the game, with the client's deductions as hints. The client is shown every
+// turn's results, but not the player's flags, so its hints are always right.
+fn play_interactive(game: GameOptions, variant: Variant) -> Result<(), GameError> {
+    let mut server = new_game(&game, &variant)?;
+    let dims = server.dims.clone();
+    let mut slice = vec![0; dims.len().saturating_sub(2)];
+    let mut client = Client::with_options(&mut server, variant.options);
+    let mut hint = None;
+    let stdin = stdin();
+    let mut lines = stdin.lock().lines();
+
+    println!("{}", INTERACTIVE_HELP);
+
+    loop {
+        let server = client.server();
+
+        println!();
+        print_slice(server, hint.as_ref(), &slice);
+        println!("{} cells left, {} mines", server.cells_rem, server.mines);
+        print!("> ");
+        stdout().flush()?;
+
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => {
+                println!();
+                break;
+            }
+        };
+        let mut words = line.trim().splitn(2, ' ');
+        let name = words.next().unwrap_or("");
+        let command = (name, words.next().unwrap_or("").trim());
+
+        let turn = match command {
+            ("c", arg) | ("f", arg) | ("u", arg) => match parse_coords(arg, &slice, &dims) {
+                Ok(coords) => {
+                    let (mut clear, mut flag, mut unflag) = (Vec::new(), Vec::new(), Vec::new());
+                    slice = coords.0[dims.len() - slice.len()..].to_vec();
+
+                    match name {
+                        "c" => clear.push(coords),
+                        "f" => flag.push(coords),
+                        _ => unflag.push(coords),
+                    }
+
+                    Some((clear, flag, unflag))
+                }
+                Err(e) => {
+                    println!("{}", e);
+                    None
+                }
+            },
+            ("h", _) => {
+                let overlay = client.overlay();
+
+                match overlay.safe.iter().min() {
+                    Some(&index) => {
+                        let coords = Coords::from_index(index, &dims);
+                        println!("{} is safe", coords);
+
+                        slice = coords.0[dims.len() - slice.len()..].to_vec();
+                        hint = Some(SolverOverlay {
+                            safe: Some(index).into_iter().collect(),
+                            ..Default::default()
+                        });
+                    }
+                    None => println!("No cell is provably safe; you'll have to guess"),
+                }
+
+                None
+            }
+            ("s", arg) => {
+                match parse_coords(arg, &[], &dims[dims.len() - slice.len()..]) {
+                    Ok(coords) => slice = coords.0,
+                    Err(e) => println!("{}", e),
+                }
+
+                None
+            }
+            ("n", _) if !slice.is_empty() => {
+                slice = step_slice(&dims, &slice, 1);
+                None
+            }
+            ("p", _) if !slice.is_empty() => {
+                slice = step_slice(&dims, &slice, -1);
+                None
+            }
+            ("q", _) => break,
+            ("", _) => None,
+            _ => {
+                println!("{}", INTERACTIVE_HELP);
+                None
+            }
+        };
+
+        if let Some((clear, flag, unflag)) = turn {
+            hint = None;
+
+            match client.take_turn(clear, flag, unflag) {
+                Ok(true) => (),
+                Ok(false) => break,
+                Err(e) => println!("{}", e),
+            }
+        }
+    }
+
+    // Every slice is shown at the end, in case the losing mine isn't on the one in view
+    let server = client.server();
+
+    if server.game_state != GameState::Ongoing {
+        println!(
+            "\n{}\n{:?}",
+            AnsiRenderer::default().render(server, None),
+            server.game_state
+        );
+    }
+
+    Ok(())
+}
+
+pub fn play(options: PlayOptions) -> Result<(), GameError> {
+    let PlayOptions {
+        game,
+        variant,
+        interactive,
+    } = options;
+
+    if interactive {
+        return play_interactive(game, variant);
+    }
+
+    let mut server = new_game(&game, &variant)?;
+    let renderer = AnsiRenderer::default();
+    let mut client = Client::with_options(&mut server, variant.options);
+    let mut turn = 0;
+
+    loop {
+        let overlay = client.overlay();
+
+        println!(
+            "Turn {}{}\n{}",
+            turn,
+            if client.guessing() { " (guessing)" } else { "" },
+            renderer.render(client.server(), Some(&overlay))
+        );
+
+        if !client.turn()? {
+            break;
+        }
+
+        turn += 1;
+    }
+
+    println!(
+        "{:?} after {} turns\n{}",
+        client.server().game_state,
+        turn + 1,
+        renderer.render(client.server(), None)
+    );
+
+    Ok(())
+}
+
+pub fn replay(options: GameOptions) -> Result<(), GameError> {
+    print_replay(&Replay::run(options.spec()?)?);
+
+    Ok(())
+}
+
+pub fn solve(options: SolveOptions) -> Result<(), GameError> {
+    let SolveOptions { layout, variant } = options;
+    let layout = Layout::read(&layout)?;
+    let server = NativeServer::from_layout(&layout, variant.autoclear.unwrap_or(true), true)?;
+
+    print_metrics(&server);
+
+    let replay = Replay::play(server, variant.options)?;
+    let guesses = replay.solver.iter().filter(|step| step.guess).count();
+
+    print_replay(&replay);
+    println!("{} turns, {} guesses", replay.solver.len(), guesses);
+
+    Ok(())
+}
+
+pub fn render(options: RenderOptions) -> Result<(), GameError> {
+    let RenderOptions {
+        game,
+        ansi,
+        layout,
+        metrics,
+    } = options;
+    let server = new_game(&game, &Variant::default())?;
+    let text = TextRenderer {
+        reveal_mines: true,
+        ..Default::default()
+    };
+
+    if layout {
+        println!("{}", server.layout().repr());
+    } else if ansi {
+        println!("{}", AnsiRenderer { text }.render(&server, None));
+    } else {
+        println!("{}", text.render(&server));
+    }
+
+    if metrics {
+        print_metrics(&server);
+    }
+
+    Ok(())
+}
+
+pub fn serve(options: ServeOptions) -> Result<(), GameError> {
+    eprintln!("Serving games on {}", options.addr);
+
+    ApiHost::new().serve(options.addr.as_str())
+}
@@ -1,70 +1,26 @@
+mod batch;
+mod game;
 mod options;
 
-use chrono::Utc;
-use mines_rs::{mongodb_connector, GameBatch, JsServerWrapper, NativeServer, SpecResult};
+use std::process::exit;
 use structopt::StructOpt;
 
-use crate::options::{RunBatchOptions, ServerType};
+use crate::options::Command;
 
 fn main() {
-    let RunBatchOptions {
-        count_per_spec,
-        dims_range,
-        mines_range,
-        metaseed,
-        server_type,
-        save_to_db,
-    } = RunBatchOptions::from_args();
-
-    let batch = GameBatch {
-        count_per_spec,
-        dims_range,
-        mines_range,
-        autoclear: true,
-        metaseed,
-    };
-
-    let start = Utc::now();
-
-    let results = match (server_type, save_to_db) {
-        (ServerType::Native, false) => batch
-            .run(|spec| NativeServer::new(spec, false), |_game| ())
-            .unwrap(),
-        (ServerType::Native, true) => batch
-            .run(
-                |spec| NativeServer::new(spec, true),
-                |game| {
-                    mongodb_connector::insert_game(&game).unwrap();
-                },
-            )
-            .unwrap(),
-        (ServerType::Js, false) => batch.run(JsServerWrapper::new, |_game| ()).unwrap(),
-        (ServerType::Js, true) => panic!("save_to_db command line option is invalid for JS server"),
+    let result = match Command::from_args() {
+        Command::Batch(options) => batch::batch(options),
+        Command::Compare(options) => batch::compare(options),
+        Command::Merge(options) => batch::merge(options),
+        Command::Play(options) => game::play(options),
+        Command::Replay(options) => game::replay(options),
+        Command::Solve(options) => game::solve(options),
+        Command::Render(options) => game::render(options),
+        Command::Serve(options) => game::serve(options),
     };
 
-    let game_count = results.len() * count_per_spec;
-
-    println!("Dims\t\tMines\tWins/Played");
-
-    for SpecResult {
-        dims,
-        mines,
-        wins,
-        played,
-        info: _,
-    } in results
-    {
-        let win_perc = wins as f64 * 100f64 / count_per_spec as f64;
-        println!(
-            "{:?}\t{}:\t{}/{}\t({:.0}%)",
-            dims, mines, wins, played, win_perc
-        );
+    if let Err(e) = result {
+        eprintln!("\rError: {}", e);
+        exit(1);
     }
-
-    let stop = Utc::now();
-    let dur_us = (stop - start).num_microseconds().unwrap();
-    let dur_s = dur_us as f64 / 1_000_000_f64;
-    let avg_us = dur_us / game_count as i64;
-
-    println!("Time: {:.2}s (avg {}µs/game/core)", dur_s, avg_us);
 }
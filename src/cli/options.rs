@@ -1,6 +1,8 @@
+use mines_rs::{BoardRng, GameError, GameSpec, GuessStrategy, MinesSpec, Opening, Variant};
 use std::error::Error;
 use std::iter::StepBy;
 use std::ops::RangeInclusive;
+use std::path::PathBuf;
 use structopt::StructOpt;
 
 type RangeOpt = StepBy<RangeInclusive<usize>>;
@@ -17,20 +19,114 @@ fn parse_range(s: &str) -> Result<RangeOpt, Box<dyn Error>> {
     };
     let step = match sub_args.next() {
         Some(step) => step.parse()?,
-        None => {
-            return Ok((lower..=upper).step_by(1));
-        }
+        None => 1,
     };
 
+    if upper < lower || step == 0 {
+        Err(format!("Range {} has no end", s))?;
+    }
+
     Ok((lower..=upper).step_by(step))
 }
 
-fn parse_mines_range(s: &str) -> Result<RangeOpt, &str> {
-    parse_range(s).or(Err(
-        "Mines range should be of the form start[..end][..step], e.g. `10` `10..50..5`",
+/// Mine counts or densities, in batch order.
+#[derive(Debug)]
+pub struct MinesRange(pub Vec<MinesSpec>);
+
+// A density is given as a percentage (`15%`) or a fraction (`0.15`)
+fn parse_density(s: &str) -> Result<f64, Box<dyn Error>> {
+    let density = if s.ends_with('%') {
+        s.trim_end_matches('%').parse::<f64>()? / 100.0
+    } else {
+        s.parse()?
+    };
+
+    if density >= 0.0 && density < 1.0 {
+        Ok(density)
+    } else {
+        Err(format!("Density should be from 0 up to 1, not {}", s))?
+    }
+}
+
+fn parse_ci_width(s: &str) -> Result<f64, &str> {
+    parse_density(s).or(Err(
+        "CI width should be a percentage or a fraction, e.g. `5%` `0.05`",
+    ))
+}
+
+fn parse_density_range(s: &str) -> Result<Vec<MinesSpec>, Box<dyn Error>> {
+    // Catches ranges such as `5..50%`, where the count was meant as a percentage
+    let percent = s.split("..").next().unwrap().ends_with('%');
+
+    if s.split("..")
+        .any(|sub_arg| sub_arg.ends_with('%') != percent)
+    {
+        Err(format!(
+            "Densities should all be percentages or all be fractions, not {}",
+            s
+        ))?;
+    }
+
+    let mut sub_args = s.split("..");
+
+    let lower = parse_density(sub_args.next().unwrap())?;
+    let upper = match sub_args.next() {
+        Some(upper) => parse_density(upper)?,
+        None => lower,
+    };
+    let step = match sub_args.next() {
+        Some(step) => parse_density(step)?,
+        None => upper - lower,
+    };
+
+    if upper < lower || (step == 0.0 && upper > lower) {
+        Err(format!("Density range {} has no end", s))?;
+    }
+
+    // Count the steps up front, so that rounding errors don't add or lose a density at the end
+    let steps = if step > 0.0 {
+        ((upper - lower) / step + 1e-9).floor() as usize
+    } else {
+        0
+    };
+
+    Ok((0..=steps)
+        .map(|i| MinesSpec::Density(lower + i as f64 * step))
+        .collect())
+}
+
+fn parse_mines_range(s: &str) -> Result<MinesRange, &str> {
+    let is_density = s.replace("..", "").contains(&['.', '%'][..]);
+
+    let mines_range = if is_density {
+        parse_density_range(s)
+    } else {
+        parse_range(s).map(|range| range.map(MinesSpec::Count).collect())
+    };
+
+    mines_range.map(MinesRange).or(Err(
+        "Mines range should be of the form start[..end][..step], as mine counts or densities, \
+         e.g. `10` `10..50..5` `10%..20%..2.5%` `0.1..0.2`",
+    ))
+}
+
+fn parse_mines(s: &str) -> Result<MinesSpec, &str> {
+    let mines = if s.contains(&['.', '%'][..]) {
+        parse_density(s).map(MinesSpec::Density)
+    } else {
+        s.parse().map(MinesSpec::Count).map_err(Into::into)
+    };
+
+    mines.or(Err(
+        "Mines should be a count or a density, e.g. `40` `10%` `0.1`",
     ))
 }
 
+fn parse_dim(s: &str) -> Result<usize, &str> {
+    s.parse()
+        .or(Err("Dims should be in the form size[,...], e.g. `16,16`"))
+}
+
 fn parse_dims_range(s: &str) -> Result<RangeOpt, &str> {
     parse_range(s).or(Err(
         "Dims should be in the form start[..end][..step][,...], e.g. `15` `2..5,10..30..10`",
@@ -43,6 +139,71 @@ fn parse_seed(s: &str) -> Result<u32, &str> {
     ))
 }
 
+fn parse_board_rng(s: &str) -> Result<BoardRng, &str> {
+    BoardRng::from_name(s).ok_or("Unknown RNG; expected mt19937-js-v1, pcg32-v1 or chacha20-v1")
+}
+
+/// A shard of a batch; `index` is numbered from 0.
+#[derive(Debug)]
+pub struct ShardOpt {
+    pub index: usize,
+    pub count: usize,
+}
+
+// Numbered from 1 on the command line
+fn parse_shard(s: &str) -> Result<ShardOpt, &str> {
+    let err = "Shard should be of the form n/count, from 1/count to count/count, e.g. `2/4`";
+    let mut sub_args = s.split('/');
+
+    let number: usize = sub_args.next().unwrap().parse().or(Err(err))?;
+    let count: usize = sub_args.next().ok_or(err)?.parse().or(Err(err))?;
+
+    if number == 0 || number > count || sub_args.next().is_some() {
+        return Err(err);
+    }
+
+    Ok(ShardOpt {
+        index: number - 1,
+        count,
+    })
+}
+
+fn parse_variant(s: &str) -> Result<Variant, String> {
+    let mut variant = Variant {
+        name: s.to_owned(),
+        ..Default::default()
+    };
+
+    if s == "default" {
+        return Ok(variant);
+    }
+
+    for setting in s.split(',') {
+        let mut sub_args = setting.splitn(2, '=');
+        let key = sub_args.next().unwrap();
+        let value = sub_args.next().unwrap_or("");
+
+        match (key, value) {
+            ("guess", "first") => variant.options.guess = GuessStrategy::First,
+            ("guess", _) if value.starts_with("random:") => {
+                let seed = parse_seed(value.trim_start_matches("random:"))?;
+                variant.options.guess = GuessStrategy::Random { seed };
+            }
+            ("opening", "centre") => variant.options.opening = Opening::Centre,
+            ("opening", "corner") => variant.options.opening = Opening::Corner,
+            ("autoclear", _) => {
+                let autoclear = value
+                    .parse()
+                    .or(Err("autoclear should be `true` or `false`"))?;
+                variant.autoclear = Some(autoclear);
+            }
+            _ => return Err(format!("Unknown variant setting `{}`", setting)),
+        }
+    }
+
+    Ok(variant)
+}
+
 fn parse_server_type(s: &str) -> Result<ServerType, &str> {
     match s.to_lowercase().as_str() {
         "js" => Ok(ServerType::Js),
@@ -52,7 +213,46 @@ fn parse_server_type(s: &str) -> Result<ServerType, &str> {
 }
 
 #[derive(StructOpt, Debug)]
-pub struct RunBatchOptions {
+#[structopt(name = "mines-rs-cli")]
+pub enum Command {
+    /// Plays a batch of games, printing the results of each spec as it's finished
+    #[structopt(name = "batch")]
+    Batch(BatchOptions),
+
+    /// Plays every game of a batch with two variants of the client, and compares them
+    #[structopt(name = "compare")]
+    Compare(CompareOptions),
+
+    /// Merges the result files of every shard of a batch, and prints the batch's results
+    #[structopt(name = "merge")]
+    Merge(MergeOptions),
+
+    /// Plays a single game with the client, showing the board after each turn, or lets you play
+    /// it with -i
+    #[structopt(name = "play")]
+    Play(PlayOptions),
+
+    /// Plays a single game again, e.g. a lost game from a batch, with the solver's view of each
+    /// turn
+    #[structopt(name = "replay")]
+    Replay(GameOptions),
+
+    /// Plays a board with a fixed mine layout, and shows how the client got on
+    #[structopt(name = "solve")]
+    Solve(SolveOptions),
+
+    /// Prints a game's board, with its mines
+    #[structopt(name = "render")]
+    Render(RenderOptions),
+
+    /// Hosts native games with the same HTTP API as the node.js server
+    #[structopt(name = "serve")]
+    Serve(ServeOptions),
+}
+
+/// The games of a batch.
+#[derive(StructOpt, Debug)]
+pub struct BatchSpecOptions {
     #[structopt(short = "c", default_value = "100")]
     pub count_per_spec: usize,
 
@@ -69,7 +269,7 @@ pub struct RunBatchOptions {
         parse(try_from_str = "parse_mines_range"),
         default_value = "10..50..5"
     )]
-    pub mines_range: RangeOpt,
+    pub mines_range: MinesRange,
 
     #[structopt(
         short = "s",
@@ -78,6 +278,28 @@ pub struct RunBatchOptions {
     )]
     pub metaseed: u32,
 
+    #[structopt(
+        short = "r",
+        default_value = "mt19937-js-v1",
+        parse(try_from_str = "parse_board_rng"),
+        help = "Board RNG: \"mt19937-js-v1\", \"pcg32-v1\" or \"chacha20-v1\""
+    )]
+    pub rng: BoardRng,
+
+    #[structopt(
+        short = "f",
+        parse(from_os_str),
+        help = "Batch config file (.toml or .json) to run instead of the -c, -d, -m, -s and -r \
+                options"
+    )]
+    pub config: Option<PathBuf>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct BatchOptions {
+    #[structopt(flatten)]
+    pub spec: BatchSpecOptions,
+
     #[structopt(
         short = "t",
         default_value = "native",
@@ -91,6 +313,206 @@ pub struct RunBatchOptions {
         help = "Save to database (only valid for native server type)"
     )]
     pub save_to_db: bool,
+
+    #[structopt(
+        short = "k",
+        parse(from_os_str),
+        help = "Checkpoint file to save finished specs to, and resume the batch from"
+    )]
+    pub checkpoint: Option<PathBuf>,
+
+    #[structopt(short = "l", help = "Print the seeds of each spec's lost games")]
+    pub lost_seeds: bool,
+
+    #[structopt(
+        short = "S",
+        parse(try_from_str = "parse_shard"),
+        help = "Play only this shard of the batch, e.g. `2/4`, and print its results as JSON for \
+                merging"
+    )]
+    pub shard: Option<ShardOpt>,
+
+    #[structopt(
+        short = "B",
+        help = "Divide shards by whole specs, rather than by equal numbers of games"
+    )]
+    pub shard_by_spec: bool,
+
+    #[structopt(
+        short = "w",
+        parse(try_from_str = "parse_ci_width"),
+        help = "Play each spec until its 95% CI is this wide, e.g. `5%` or `0.05`, with -c as the \
+                most games per spec"
+    )]
+    pub ci_width: Option<f64>,
+
+    #[structopt(
+        short = "n",
+        default_value = "100",
+        help = "Games to play between checks of the CI width, for -w"
+    )]
+    pub round_size: usize,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CompareOptions {
+    #[structopt(flatten)]
+    pub spec: BatchSpecOptions,
+
+    #[structopt(
+        short = "t",
+        default_value = "native",
+        parse(try_from_str = "parse_server_type"),
+        help = "\"js\" or \"native\""
+    )]
+    pub server_type: ServerType,
+
+    #[structopt(
+        parse(try_from_str = "parse_variant"),
+        help = "Variant A: `default`, or any of `guess=first|random:SEED`, \
+                `opening=centre|corner` and `autoclear=true|false`, separated by commas"
+    )]
+    pub a: Variant,
+
+    #[structopt(parse(try_from_str = "parse_variant"), help = "Variant B, as for A")]
+    pub b: Variant,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct MergeOptions {
+    #[structopt(short = "l", help = "Print the seeds of each spec's lost games")]
+    pub lost_seeds: bool,
+
+    #[structopt(
+        parse(from_os_str),
+        raw(required = "true"),
+        help = "Result files of the shards, in any order"
+    )]
+    pub shards: Vec<PathBuf>,
+}
+
+/// A single game.
+#[derive(StructOpt, Debug)]
+pub struct GameOptions {
+    #[structopt(
+        short = "d",
+        parse(try_from_str = "parse_dim"),
+        raw(use_delimiter = "true"),
+        default_value = "20,20"
+    )]
+    pub dims: Vec<usize>,
+
+    #[structopt(
+        short = "m",
+        parse(try_from_str = "parse_mines"),
+        default_value = "40",
+        help = "Mine count or density, e.g. `40` `10%` `0.1`"
+    )]
+    pub mines: MinesSpec,
+
+    #[structopt(
+        short = "s",
+        default_value = "133337",
+        parse(try_from_str = "parse_seed")
+    )]
+    pub seed: u32,
+
+    #[structopt(
+        short = "r",
+        default_value = "mt19937-js-v1",
+        parse(try_from_str = "parse_board_rng"),
+        help = "Board RNG: \"mt19937-js-v1\", \"pcg32-v1\" or \"chacha20-v1\""
+    )]
+    pub rng: BoardRng,
+
+    #[structopt(long = "no-autoclear")]
+    pub no_autoclear: bool,
+}
+
+impl GameOptions {
+    pub fn spec(&self) -> Result<GameSpec, GameError> {
+        let size = self.dims.iter().product();
+        let mines = match self.mines.mines(size) {
+            Some(mines) if !self.dims.is_empty() => mines,
+            _ => Err(format!(
+                "Invalid game: dims={:?} mines={}",
+                self.dims, self.mines
+            ))?,
+        };
+
+        Ok(GameSpec {
+            dims: self.dims.clone(),
+            mines,
+            seed: self.seed,
+            autoclear: !self.no_autoclear,
+            rng: self.rng,
+        })
+    }
+}
+
+#[derive(StructOpt, Debug)]
+pub struct PlayOptions {
+    #[structopt(flatten)]
+    pub game: GameOptions,
+
+    #[structopt(
+        short = "v",
+        default_value = "default",
+        parse(try_from_str = "parse_variant"),
+        help = "Client variant, as for `compare`"
+    )]
+    pub variant: Variant,
+
+    #[structopt(
+        short = "i",
+        help = "Play the game yourself in the terminal, with hints from the client"
+    )]
+    pub interactive: bool,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct SolveOptions {
+    #[structopt(
+        parse(from_os_str),
+        help = "Mine layout file, with `*` for mines and `.` for empty cells"
+    )]
+    pub layout: PathBuf,
+
+    #[structopt(
+        short = "v",
+        default_value = "default",
+        parse(try_from_str = "parse_variant"),
+        help = "Client variant, as for `compare`"
+    )]
+    pub variant: Variant,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct RenderOptions {
+    #[structopt(flatten)]
+    pub game: GameOptions,
+
+    #[structopt(long = "ansi", help = "Colour the board with ANSI escape codes")]
+    pub ansi: bool,
+
+    #[structopt(
+        long = "layout",
+        help = "Print the board as a mine layout, e.g. to save for `solve`"
+    )]
+    pub layout: bool,
+
+    #[structopt(long = "metrics", help = "Print the board's difficulty metrics too")]
+    pub metrics: bool,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct ServeOptions {
+    #[structopt(
+        short = "a",
+        default_value = "localhost:1066",
+        help = "Address to listen on; clients expect `localhost:1066`"
+    )]
+    pub addr: String,
 }
 
 #[derive(Debug)]
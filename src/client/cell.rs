@@ -1,7 +1,7 @@
 use std::cmp::{max, min};
-use std::collections::HashSet;
 
 use crate::client::action_queue::ActionQueue;
+use crate::game_grid::Neighbours;
 
 #[derive(Clone, Copy, Debug)]
 pub enum Action {
@@ -38,18 +38,17 @@ pub enum Cell {
 #[derive(Clone, Debug)]
 pub struct OngoingCell {
     index: usize,
-    unknown_surr: HashSet<usize>,
-    total_surr: HashSet<usize>,
+    // Slots of surrounding cells, as given by the cell's `Neighbours`
+    unknown_surr: SlotSet,
     total_surr_mines: Option<usize>,
     known_surr_mines: usize,
 }
 
 impl Cell {
-    pub fn new(index: usize, surr_indices: HashSet<usize>) -> Self {
+    pub fn new(index: usize, surr_count: usize) -> Self {
         Cell::Ongoing(OngoingCell {
             index,
-            unknown_surr: surr_indices.clone(),
-            total_surr: surr_indices,
+            unknown_surr: SlotSet::full(surr_count),
             total_surr_mines: None,
             known_surr_mines: 0,
         })
@@ -74,28 +73,33 @@ impl OngoingCell {
     }
 
     // Returns true if cell is Complete as a result of action.
-    pub fn apply_action(&mut self, actions: &mut ActionQueue, action: SingleCellAction) -> bool {
+    pub fn apply_action(
+        &mut self,
+        surr: Neighbours,
+        actions: &mut ActionQueue,
+        action: SingleCellAction,
+    ) -> bool {
         use self::SingleCellAction::*;
 
         match action {
-            MarkSurrEmpty { surr } => {
-                self.mark_surr_empty(surr);
-                self.try_complete(actions)
+            MarkSurrEmpty { surr: index } => {
+                self.mark_surr_empty(surr, index);
+                self.try_complete(surr, actions)
             }
-            MarkSurrMine { surr } => {
-                self.mark_surr_mine(surr);
-                self.try_complete(actions)
+            MarkSurrMine { surr: index } => {
+                self.mark_surr_mine(surr, index);
+                self.try_complete(surr, actions)
             }
             ClientClear { mines } => {
-                self.client_clear(actions, mines);
-                self.try_complete(actions)
+                self.client_clear(surr, actions, mines);
+                self.try_complete(surr, actions)
             }
             ServerClear => {
-                self.server_clear(actions);
-                self.try_complete(actions)
+                self.server_clear(surr, actions);
+                self.try_complete(surr, actions)
             }
             Flag => {
-                self.flag(actions);
+                self.flag(surr, actions);
                 true
             }
         }
@@ -103,7 +107,9 @@ impl OngoingCell {
 
     pub fn apply_pair_action(
         &mut self,
+        self_surr: Neighbours,
         other: &mut OngoingCell,
+        other_surr: Neighbours,
         actions: &mut ActionQueue,
         action: CellPairAction,
     ) {
@@ -111,24 +117,29 @@ impl OngoingCell {
 
         match action {
             CompareSurr => {
-                self.compare_surr(other, actions);
+                self.compare_surr(self_surr, other, other_surr, actions);
             }
         }
     }
 
-    fn mark_surr_empty(&mut self, surr: usize) {
-        self.unknown_surr.remove(&surr);
+    fn mark_surr_empty(&mut self, surr: Neighbours, index: usize) {
+        if let Some(slot) = surr.slot(index) {
+            self.unknown_surr.remove(slot);
+        }
     }
 
-    fn mark_surr_mine(&mut self, surr: usize) {
-        self.unknown_surr.remove(&surr);
+    fn mark_surr_mine(&mut self, surr: Neighbours, index: usize) {
+        if let Some(slot) = surr.slot(index) {
+            self.unknown_surr.remove(slot);
+        }
+
         self.known_surr_mines += 1;
     }
 
-    fn client_clear(&mut self, actions: &mut ActionQueue, mines: usize) {
+    fn client_clear(&mut self, surr: Neighbours, actions: &mut ActionQueue, mines: usize) {
         self.total_surr_mines = Some(mines);
 
-        for &surr in self.total_surr.iter() {
+        for surr in surr.iter() {
             actions.push(Action::Single {
                 index: surr,
                 action_type: SingleCellAction::MarkSurrEmpty { surr: self.index },
@@ -136,14 +147,14 @@ impl OngoingCell {
         }
     }
 
-    fn server_clear(&mut self, actions: &mut ActionQueue) {
+    fn server_clear(&mut self, surr: Neighbours, actions: &mut ActionQueue) {
         if self.total_surr_mines == None {
             actions.add_to_clear(self.index);
         }
 
         // Not necessary to mark as empty at this point, but allows for
         // some more eager clearing/flagging on the current turn
-        for &surr in self.total_surr.iter() {
+        for surr in surr.iter() {
             actions.push(Action::Single {
                 index: surr,
                 action_type: SingleCellAction::MarkSurrEmpty { surr: self.index },
@@ -151,10 +162,10 @@ impl OngoingCell {
         }
     }
 
-    fn flag(&mut self, actions: &mut ActionQueue) {
+    fn flag(&mut self, surr: Neighbours, actions: &mut ActionQueue) {
         actions.add_to_flag(self.index);
 
-        for &surr in self.total_surr.iter() {
+        for surr in surr.iter() {
             actions.push(Action::Single {
                 index: surr,
                 action_type: SingleCellAction::MarkSurrMine { surr: self.index },
@@ -162,14 +173,16 @@ impl OngoingCell {
         }
     }
 
-    fn try_complete(&mut self, actions: &mut ActionQueue) -> bool {
+    fn try_complete(&mut self, surr: Neighbours, actions: &mut ActionQueue) -> bool {
         if let Some(unknown_surr_mines) = self.unknown_surr_mines() {
-            if try_mark_cell_set(unknown_surr_mines, self.unknown_surr.iter(), actions) {
+            let unknown = self.unknown_surr.iter().map(|slot| surr.get(slot));
+
+            if try_mark_cell_set(unknown_surr_mines, unknown, actions) {
                 return true;
             }
         }
 
-        for &surr in self.total_surr.iter() {
+        for surr in surr.iter() {
             actions.push(Action::Pair {
                 index1: self.index,
                 index2: surr,
@@ -180,13 +193,24 @@ impl OngoingCell {
         return false;
     }
 
-    fn compare_surr(&mut self, other: &mut OngoingCell, actions: &mut ActionQueue) {
+    fn compare_surr(
+        &mut self,
+        self_surr: Neighbours,
+        other: &mut OngoingCell,
+        other_surr: Neighbours,
+        actions: &mut ActionQueue,
+    ) {
         if let (Some(self_unknown_mines), Some(other_unknown_mines)) =
             (self.unknown_surr_mines(), other.unknown_surr_mines())
         {
-            let self_excl = || self.unknown_surr.difference(&other.unknown_surr);
-            let other_excl = || other.unknown_surr.difference(&self.unknown_surr);
-            let common = || self.unknown_surr.intersection(&other.unknown_surr);
+            let self_unknown = || self.unknown_surr.iter().map(move |s| self_surr.get(s));
+            let other_unknown = || other.unknown_surr.iter().map(move |s| other_surr.get(s));
+            let in_self = |i| self.unknown_surr.contains_index(self_surr, i);
+            let in_other = |i| other.unknown_surr.contains_index(other_surr, i);
+
+            let self_excl = || self_unknown().filter(move |&i| !in_other(i));
+            let other_excl = || other_unknown().filter(move |&i| !in_self(i));
+            let common = || self_unknown().filter(move |&i| in_other(i));
 
             if let Some((self_count, mid_count, other_count)) = solve_linear_constraints(
                 (self_excl().count(), common().count(), other_excl().count()),
@@ -200,7 +224,7 @@ impl OngoingCell {
     }
 }
 
-fn try_mark_cell_set<'a, I: Iterator<Item = &'a usize> + Clone>(
+fn try_mark_cell_set<I: Iterator<Item = usize> + Clone>(
     mine_count: usize,
     set_iter: I,
     actions: &mut ActionQueue,
@@ -213,13 +237,68 @@ fn try_mark_cell_set<'a, I: Iterator<Item = &'a usize> + Clone>(
         }
     };
 
-    for &index in set_iter {
+    for index in set_iter {
         actions.push(Action::Single { index, action_type });
     }
 
     return true;
 }
 
+// Bitset of neighbour slots. Cells of up to 3 dimensions have at most 26 neighbours, so only
+// larger neighbourhoods need the heap.
+#[derive(Clone, Debug)]
+struct SlotSet {
+    first: u64,
+    rest: Vec<u64>,
+}
+
+impl SlotSet {
+    fn full(len: usize) -> Self {
+        // Bits for slots `start..start + 64`
+        let block = |start: usize| match len.saturating_sub(start) {
+            0 => 0,
+            n if n >= 64 => !0,
+            n => (1 << n) - 1,
+        };
+
+        SlotSet {
+            first: block(0),
+            rest: (64..len).step_by(64).map(block).collect(),
+        }
+    }
+
+    fn block(&self, slot: usize) -> &u64 {
+        match slot / 64 {
+            0 => &self.first,
+            b => &self.rest[b - 1],
+        }
+    }
+
+    fn contains(&self, slot: usize) -> bool {
+        slot < 64 * (self.rest.len() + 1) && self.block(slot) & (1 << (slot % 64)) != 0
+    }
+
+    fn contains_index(&self, surr: Neighbours, index: usize) -> bool {
+        match surr.slot(index) {
+            Some(slot) => self.contains(slot),
+            None => false,
+        }
+    }
+
+    fn remove(&mut self, slot: usize) {
+        let block = match slot / 64 {
+            0 => &mut self.first,
+            b => &mut self.rest[b - 1],
+        };
+
+        *block &= !(1 << (slot % 64));
+    }
+
+    fn iter<'a>(&'a self) -> impl Iterator<Item = usize> + Clone + 'a {
+        (0..64 * (self.rest.len() + 1)).filter(move |&slot| self.contains(slot))
+    }
+}
+
 fn solve_linear_constraints(
     (x_max, y_max, z_max): (usize, usize, usize),
     (x_add_y, y_add_z): (usize, usize),
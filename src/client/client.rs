@@ -1,3 +1,8 @@
+use mersenne_twister::MT19937;
+use rand::{Rng, SeedableRng};
+use std::collections::HashSet;
+use std::mem;
+
 use crate::client::action_queue::ActionQueue;
 use crate::client::cell::{Action, Cell, SingleCellAction};
 use crate::coords::Coords;
@@ -5,42 +10,192 @@ use crate::game_grid::GameGrid;
 use crate::server::{CellInfo, GameServer, GameState};
 use crate::GameError;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 struct ServerActions {
     to_clear: Vec<Coords>,
     to_flag: Vec<Coords>,
+    // Whether `to_clear` is a guess rather than a deduction
+    guess: bool,
+}
+
+/// Cells which the client has deduced to be safe or to be mines, but hasn't yet acted on.
+#[derive(Clone, Debug, Default)]
+pub struct SolverOverlay {
+    pub safe: HashSet<usize>,
+    pub mines: HashSet<usize>,
+}
+
+/// How the client picks a cell to clear when it can't deduce a safe one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GuessStrategy {
+    /// The first unmarked cell, in index order.
+    First,
+    /// An unmarked cell picked at random, from a generator with the given seed.
+    Random { seed: u32 },
+}
+
+impl Default for GuessStrategy {
+    fn default() -> Self {
+        GuessStrategy::First
+    }
+}
+
+/// Where the client makes its first clear.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Opening {
+    /// The server's start co-ordinates, which are the centre of a bounded board.
+    Centre,
+    /// The cell with all co-ordinates 0.
+    Corner,
+}
+
+impl Default for Opening {
+    fn default() -> Self {
+        Opening::Centre
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ClientOptions {
+    pub guess: GuessStrategy,
+    pub opening: Opening,
 }
 
 pub struct Client<'a, G: GameServer + 'a> {
     grid: GameGrid<Cell>,
     server: &'a mut G,
+    pending: ServerActions,
+    guess_rng: Option<MT19937>,
 }
 
 impl<'a, G: GameServer> Client<'a, G> {
     pub fn new(server: &'a mut G) -> Self {
-        let grid = GameGrid::new(server.dims(), Cell::new);
+        Self::with_options(server, ClientOptions::default())
+    }
 
-        Client { grid, server }
+    pub fn with_options(server: &'a mut G, options: ClientOptions) -> Self {
+        let grid = match server.dims() {
+            Some(dims) => GameGrid::new(dims, Cell::new),
+            None => GameGrid::unbounded(server.dim_count(), Cell::new),
+        };
+        let start_coords = match options.opening {
+            Opening::Centre => server.start_coords(),
+            Opening::Corner => Coords(vec![0; server.dim_count()]),
+        };
+        let pending = ServerActions {
+            to_clear: vec![start_coords],
+            to_flag: vec![],
+            guess: true,
+        };
+        let guess_rng = match options.guess {
+            GuessStrategy::First => None,
+            GuessStrategy::Random { seed } => Some(SeedableRng::from_seed(seed)),
+        };
+
+        Client {
+            grid,
+            server,
+            pending,
+            guess_rng,
+        }
     }
 
     pub fn play(&mut self) -> Result<(), GameError> {
-        let mut to_clear = vec![Coords(self.server.dims().iter().map(|&d| d / 2).collect())];
-        let mut to_flag = vec![];
+        while self.turn()? {}
 
-        while !(to_clear.is_empty() && to_flag.is_empty()) {
-            let clear_actual = self.server.turn(to_clear, to_flag, vec![])?;
+        Ok(())
+    }
+
+    /// Sends the client's pending actions to the server, and works out the next actions from
+    /// the result. Returns false once the game is over.
+    pub fn turn(&mut self) -> Result<bool, GameError> {
+        let ServerActions {
+            to_clear, to_flag, ..
+        } = mem::replace(&mut self.pending, ServerActions::default());
+
+        if to_clear.is_empty() && to_flag.is_empty() {
+            return Ok(false);
+        }
+
+        let clear_actual = self.server.turn(to_clear, to_flag, vec![])?;
+
+        if self.server.game_state() != GameState::Ongoing {
+            return Ok(false);
+        }
+
+        self.pending = self.next_turn(&clear_actual);
+
+        Ok(true)
+    }
 
-            if self.server.game_state() != GameState::Ongoing {
-                break;
+    /// Takes a turn chosen by someone else, e.g. a human player, in place of the client's
+    /// pending actions, and works out the client's next actions from the result. Deductions
+    /// which the turn didn't act on are kept, so `overlay` still shows them. Flags from the turn
+    /// aren't trusted, so they don't affect any deductions. Returns false once the game is over.
+    pub fn take_turn(
+        &mut self,
+        clear: Vec<Coords>,
+        flag: Vec<Coords>,
+        unflag: Vec<Coords>,
+    ) -> Result<bool, GameError> {
+        let clear_actual = self.server.turn(clear, flag, unflag)?;
+
+        if self.server.game_state() != GameState::Ongoing {
+            self.pending = ServerActions::default();
+            return Ok(false);
+        }
+
+        let next = self.next_turn(&clear_actual);
+        let previous = mem::replace(&mut self.pending, next);
+        let cleared: HashSet<&Coords> = clear_actual.iter().map(|cell| &cell.coords).collect();
+        let pending = &mut self.pending;
+
+        if !previous.guess {
+            let safe: Vec<Coords> = previous
+                .to_clear
+                .into_iter()
+                .filter(|coords| !cleared.contains(coords) && !pending.to_clear.contains(coords))
+                .collect();
+
+            // A deduction beats a guess
+            if pending.guess && !safe.is_empty() {
+                pending.to_clear.clear();
+                pending.guess = false;
             }
 
-            let next_actions = self.next_turn(&clear_actual);
+            pending.to_clear.extend(safe);
+        }
 
-            to_clear = next_actions.to_clear;
-            to_flag = next_actions.to_flag;
+        for coords in previous.to_flag {
+            if !pending.to_flag.contains(&coords) {
+                pending.to_flag.push(coords);
+            }
         }
 
-        Ok(())
+        Ok(true)
+    }
+
+    pub fn server(&self) -> &G {
+        self.server
+    }
+
+    /// Whether the next turn's clear is a guess rather than a deduction.
+    pub fn guessing(&self) -> bool {
+        self.pending.guess
+    }
+
+    pub fn overlay(&self) -> SolverOverlay {
+        let to_indices =
+            |coords: &[Coords]| coords.iter().filter_map(|c| self.grid.find(c)).collect();
+
+        SolverOverlay {
+            safe: if self.pending.guess {
+                HashSet::new()
+            } else {
+                to_indices(&self.pending.to_clear)
+            },
+            mines: to_indices(&self.pending.to_flag),
+        }
     }
 
     fn next_turn(&mut self, clear_actual: &[CellInfo]) -> ServerActions {
@@ -52,7 +207,7 @@ impl<'a, G: GameServer> Client<'a, G> {
             mine,
         } in clear_actual.iter()
         {
-            let index = coords.to_index(&self.server.dims());
+            let index = self.grid.index(coords);
             let action_type = if mine {
                 SingleCellAction::Flag
             } else {
@@ -65,11 +220,11 @@ impl<'a, G: GameServer> Client<'a, G> {
         while let Some(action) = actions.pull() {
             match action {
                 Action::Single { index, action_type } => {
-                    let cell = &mut self.grid[index];
+                    let (cell, surr) = self.grid.cell_mut(index);
                     let mut complete = false;
 
                     if let &mut Cell::Ongoing(ref mut ongoing) = cell {
-                        complete = ongoing.apply_action(&mut actions, action_type);
+                        complete = ongoing.apply_action(surr, &mut actions, action_type);
                     }
 
                     if complete {
@@ -81,40 +236,166 @@ impl<'a, G: GameServer> Client<'a, G> {
                     index2,
                     action_type,
                 } => match self.grid.cell_pair(index1, index2) {
-                    (&mut Cell::Ongoing(ref mut cell1), &mut Cell::Ongoing(ref mut cell2)) => {
-                        cell1.apply_pair_action(cell2, &mut actions, action_type);
+                    (
+                        (&mut Cell::Ongoing(ref mut cell1), surr1),
+                        (&mut Cell::Ongoing(ref mut cell2), surr2),
+                    ) => {
+                        cell1.apply_pair_action(surr1, cell2, surr2, &mut actions, action_type);
                     }
                     _ => (),
                 },
             }
         }
 
-        if actions.get_to_clear().next() == None {
+        let guess = actions.get_to_clear().next() == None;
+
+        if guess {
             actions.add_to_clear(self.guess_index());
         }
 
         let next_actions = ServerActions {
             to_clear: actions
                 .get_to_clear()
-                .map(|&i| Coords::from_index(i, &self.server.dims()))
+                .map(|&i| self.grid.coords(i))
                 .collect(),
             to_flag: actions
                 .get_to_flag()
-                .map(|&i| Coords::from_index(i, &self.server.dims()))
+                .map(|&i| self.grid.coords(i))
                 .collect(),
+            guess,
         };
 
         next_actions
     }
 
-    fn guess_index(&self) -> usize {
-        let (i, _cell) = self
-            .grid
+    fn guess_index(&mut self) -> usize {
+        let Client {
+            ref grid,
+            ref mut guess_rng,
+            ..
+        } = *self;
+        let mut unmarked = grid
             .iter()
             .enumerate()
-            .find(|&(_i, cell)| !cell.is_marked())
-            .expect("Found no uncleared, unflagged cell to guess");
+            .filter(|&(_i, cell)| !cell.is_marked())
+            .map(|(i, _cell)| i);
+
+        let guess = match guess_rng {
+            None => unmarked.next(),
+            Some(rng) => {
+                let unmarked: Vec<usize> = unmarked.collect();
+
+                if unmarked.is_empty() {
+                    None
+                } else {
+                    Some(unmarked[rng.gen_range(0, unmarked.len())])
+                }
+            }
+        };
+
+        guess.expect("Found no uncleared, unflagged cell to guess")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::client::Client;
+    use crate::coords::Coords;
+    use crate::server::native::{CellAction, NativeServer};
+    use crate::server::{GameSpec, GameState};
+
+    #[test]
+    fn test_turn_overlay() {
+        for seed in 0..20 {
+            let spec = GameSpec {
+                dims: vec![8, 8],
+                mines: 10,
+                seed,
+                autoclear: true,
+                rng: Default::default(),
+            };
+            let mut server = NativeServer::new(spec.clone(), false).unwrap();
+            let mut turns = 0;
+
+            {
+                let mut client = Client::new(&mut server);
+
+                loop {
+                    let overlay = client.overlay();
+                    let server = client.server();
+
+                    // Deductions are never wrong, and there's nothing to deduce before a guess
+                    assert!(overlay.safe.iter().all(|&i| !server.cell(i).mine));
+                    assert!(overlay.mines.iter().all(|&i| server.cell(i).mine));
+                    assert!(!client.guessing() || overlay.safe.is_empty());
+
+                    turns += 1;
+
+                    if !client.turn().unwrap() {
+                        break;
+                    }
+                }
+            }
+
+            // Taking turns one at a time plays the same game as `play`
+            let mut played = NativeServer::new(spec, true).unwrap();
+            Client::new(&mut played).play().unwrap();
+
+            assert_eq!(server.game_state, played.game_state);
+            assert_eq!(server.cells_rem, played.cells_rem);
+            assert_eq!(turns, played.turns.unwrap().len() - 1);
+        }
+    }
+
+    #[test]
+    fn test_take_turn() {
+        let mut hints = 0;
+
+        for seed in 0..20 {
+            let spec = GameSpec {
+                dims: vec![8, 8],
+                mines: 10,
+                seed,
+                autoclear: true,
+                rng: Default::default(),
+            };
+            let mut server = NativeServer::new(spec, false).unwrap();
+            let mut client = Client::new(&mut server);
+
+            loop {
+                let overlay = client.overlay();
+                let server = client.server();
+
+                assert!(overlay.safe.iter().all(|&i| !server.cell(i).mine));
+                assert!(overlay.mines.iter().all(|&i| server.cell(i).mine));
+
+                // Play the hints when there are any, or otherwise a safe cell found by cheating
+                let clear = match overlay.safe.iter().min() {
+                    Some(&index) => {
+                        hints += 1;
+                        index
+                    }
+                    None => (0..64)
+                        .find(|&i| {
+                            let cell = server.cell(i);
+                            !cell.mine && cell.action == CellAction::NoAction
+                        })
+                        .unwrap(),
+                };
+                let to_coords = |index| Coords::from_index(index, &[8, 8]);
+                let flag = overlay.mines.iter().map(|&i| to_coords(i)).collect();
+
+                if !client
+                    .take_turn(vec![to_coords(clear)], flag, vec![])
+                    .unwrap()
+                {
+                    break;
+                }
+            }
+
+            assert_eq!(server.game_state, GameState::Win);
+        }
 
-        i
+        assert!(hints > 0);
     }
 }
@@ -3,4 +3,4 @@ mod cell;
 mod client;
 
 pub use self::cell::Cell;
-pub use self::client::Client;
+pub use self::client::{Client, ClientOptions, GuessStrategy, Opening, SolverOverlay};
@@ -1,8 +1,12 @@
+use itertools::Itertools;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::convert::{TryFrom, TryInto};
 use std::fmt;
+use std::str::FromStr;
 
-#[derive(Clone)]
+use crate::GameError;
+
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct Coords<T = usize>(pub Vec<T>);
 
 impl<T> Coords<T>
@@ -30,6 +34,114 @@ where
                 (acc * dim) + coord.try_into().unwrap()
             })
     }
+
+    /// As `from_index`, but gives `None` if the index lies outside of `dims` or a co-ordinate
+    /// doesn't fit in `T`.
+    pub fn try_from_index(mut index: usize, dims: &[usize]) -> Option<Self> {
+        if index >= dims.iter().product() {
+            return None;
+        }
+
+        let mut coords = vec![Default::default(); dims.len()];
+
+        for (c, d) in coords.iter_mut().zip(dims).rev() {
+            *c = T::try_from(index % d).ok()?;
+            index /= d;
+        }
+
+        Some(Coords(coords))
+    }
+
+    /// As `to_index`, but checks that the co-ordinates lie within `dims`.
+    pub fn try_to_index(&self, dims: &[usize]) -> Result<usize, GameError> {
+        if self.0.len() != dims.len() {
+            return Err(format!(
+                "Co-ordinates {} don't match {} dimensions",
+                self.repr(),
+                dims.len()
+            ))?;
+        }
+
+        let mut index = 0;
+
+        for (&coord, &dim) in self.0.iter().zip(dims.iter()) {
+            let coord = match coord.try_into() {
+                Ok(c) if c < dim => c,
+                _ => {
+                    return Err(format!(
+                        "Co-ordinates {} out of bounds for dims {:?}",
+                        self.repr(),
+                        dims
+                    ))?
+                }
+            };
+
+            index = index * dim + coord;
+        }
+
+        Ok(index)
+    }
+
+    fn repr(&self) -> String {
+        self.0
+            .iter()
+            .map(|&c| match c.try_into() {
+                Ok(c) => c.to_string(),
+                Err(_) => "?".to_string(),
+            })
+            .join(",")
+    }
+}
+
+impl Coords {
+    /// Co-ordinates of all cells surrounding this one, in index order. `self` must lie within
+    /// `dims`.
+    pub fn neighbours<'a>(&'a self, dims: &[usize]) -> impl Iterator<Item = Coords> + 'a {
+        self.0
+            .iter()
+            .zip(dims.iter())
+            .map(|(&c, &d)| c.saturating_sub(1)..(c + 2).min(d))
+            .multi_cartesian_product()
+            .filter(move |coords| coords != &self.0)
+            .map(Coords)
+    }
+
+    /// Number of king's moves between two cells.
+    pub fn chebyshev(&self, other: &Coords) -> usize {
+        self.dim_distances(other).max().unwrap_or(0)
+    }
+
+    pub fn manhattan(&self, other: &Coords) -> usize {
+        self.dim_distances(other).sum()
+    }
+
+    fn dim_distances<'a>(&'a self, other: &'a Coords) -> impl Iterator<Item = usize> + 'a {
+        self.0
+            .iter()
+            .zip(other.0.iter())
+            .map(|(&a, &b)| a.max(b) - a.min(b))
+    }
+}
+
+/// Comma-separated co-ordinates, e.g. `3,4,1`.
+impl<T: fmt::Display> fmt::Display for Coords<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0.iter().join(","))
+    }
+}
+
+impl<T: FromStr> FromStr for Coords<T> {
+    type Err = GameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let coords = s
+            .split(',')
+            .map(|c| c.trim().parse())
+            .collect::<Result<Vec<T>, _>>()
+            .map_err(|_| format!("Invalid co-ordinates {:?}; expected e.g. `3,4,1`", s))?;
+
+        Ok(Coords(coords))
+    }
 }
 
 impl<T: fmt::Debug> fmt::Debug for Coords<T> {
@@ -82,4 +194,56 @@ mod test {
             TestResult::from_bool(i == derived_index)
         }
     }
+
+    #[test]
+    fn test_coords_checked() {
+        let dims = [3, 4, 2];
+
+        let coords: Coords = Coords::try_from_index(23, &dims).unwrap();
+        assert_eq!(coords, Coords(vec![2, 3, 1]));
+        assert_eq!(coords.try_to_index(&dims).unwrap(), 23);
+        assert!(Coords::<usize>::try_from_index(24, &dims).is_none());
+        assert!(Coords::<u8>::try_from_index(300, &[400]).is_none());
+
+        assert!(Coords(vec![3, 0, 0]).try_to_index(&dims).is_err());
+        assert!(Coords(vec![0, 0]).try_to_index(&dims).is_err());
+        assert!(Coords(vec![-1i32, 0, 0]).try_to_index(&dims).is_err());
+    }
+
+    #[test]
+    fn test_coords_neighbours() {
+        let dims = [3, 4, 2];
+
+        let corner: Vec<Coords> = Coords(vec![0, 3, 1]).neighbours(&dims).collect();
+        assert_eq!(
+            corner,
+            vec![
+                Coords(vec![0, 2, 0]),
+                Coords(vec![0, 2, 1]),
+                Coords(vec![0, 3, 0]),
+                Coords(vec![1, 2, 0]),
+                Coords(vec![1, 2, 1]),
+                Coords(vec![1, 3, 0]),
+                Coords(vec![1, 3, 1]),
+            ]
+        );
+
+        let centre = Coords(vec![1, 1, 0]);
+        assert_eq!(centre.neighbours(&dims).count(), 17);
+        assert!(centre.neighbours(&dims).all(|c| c.chebyshev(&centre) == 1));
+    }
+
+    #[test]
+    fn test_coords_distance_and_repr() {
+        let a = Coords(vec![3, 4, 1]);
+        let b = Coords(vec![0, 6, 1]);
+
+        assert_eq!(a.chebyshev(&b), 3);
+        assert_eq!(a.manhattan(&b), 5);
+
+        assert_eq!(a.to_string(), "3,4,1");
+        assert_eq!(" 3, 4,1".parse::<Coords>().unwrap(), a);
+        assert!("3,x".parse::<Coords>().is_err());
+        assert!("".parse::<Coords>().is_err());
+    }
 }
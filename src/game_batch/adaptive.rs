@@ -0,0 +1,196 @@
+use mersenne_twister::MT19937;
+use rand::{Rng, SeedableRng};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::client::ClientOptions;
+use crate::game_batch::stats::{wilson_interval, SpecSamples, Z_95};
+use crate::game_batch::{
+    play_chunk, BatchConfig, BatchProgress, CancelToken, GameResult, SpecResult,
+};
+use crate::server::{GameServer, GameSpec};
+use crate::GameError;
+
+/// When `BatchConfig::run_adaptive` stops playing a spec.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Precision {
+    /// Width of the 95% confidence interval for the win rate, as a proportion, at or below which
+    /// a spec is finished.
+    pub ci_width: f64,
+    /// Number of games played between checks of the interval. Every spec has at least this many
+    /// games, unless its count is lower.
+    pub round_size: usize,
+}
+
+impl BatchConfig {
+    /// As `run_streaming`, but plays each spec in rounds until its win rate is known to
+    /// `precision`, with the spec's count as the most games to play. Progress totals shrink as
+    /// specs finish early.
+    ///
+    /// Each spec has its own stream of game seeds, drawn from a seed for the spec, so that a
+    /// spec's games don't depend on how many were played for earlier specs. The games are
+    /// therefore different from those of `run_streaming`, but just as repeatable.
+    ///
+    /// Unlike the other runs, a spec cut short by cancellation isn't passed to `on_spec`, as its
+    /// win rate isn't known to `precision`.
+    pub fn run_adaptive<G: GameServer, I: Send>(
+        self,
+        precision: Precision,
+        new_game: impl Fn(GameSpec) -> Result<G, GameError> + Sync,
+        use_game_result: impl Fn(G) -> I + Sync,
+        cancel: &CancelToken,
+        mut on_game: impl FnMut(&GameResult<I>, BatchProgress),
+        mut on_spec: impl FnMut(SpecResult<I>),
+    ) -> Result<(), GameError> {
+        if precision.round_size == 0 {
+            Err("Adaptive batch round size must be at least 1")?;
+        }
+
+        let specs = self.specs()?;
+        let mut total: usize = specs.iter().map(|spec| spec.count).sum();
+        let mut played = 0;
+
+        let BatchConfig { metaseed, rng, .. } = self;
+        let mut metaseed_rng: MT19937 = SeedableRng::from_seed(metaseed);
+
+        for (spec_index, spec) in specs.iter().enumerate() {
+            let mut seed_rng: MT19937 = SeedableRng::from_seed(metaseed_rng.next_u32());
+            let mut spec_result = SpecResult::new(spec);
+            let mut samples = SpecSamples::default();
+
+            let mut finished = false;
+
+            while !finished && !cancel.is_cancelled() {
+                let round_size = precision.round_size.min(spec.count - spec_result.played);
+                let round = (0..round_size)
+                    .map(|_| {
+                        let game_spec = GameSpec {
+                            dims: spec.dims.clone(),
+                            mines: spec.mines,
+                            seed: seed_rng.next_u32(),
+                            autoclear: spec.autoclear,
+                            rng,
+                        };
+
+                        (spec_index, game_spec)
+                    })
+                    .collect();
+
+                // Results are counted in order, so the stopping point doesn't depend on how the
+                // round's games were scheduled
+                let results = play_chunk(
+                    round,
+                    &ClientOptions::default(),
+                    &new_game,
+                    &use_game_result,
+                    cancel,
+                );
+                let round_played = results.len() == round_size;
+
+                for result in results {
+                    let result = result?;
+
+                    played += 1;
+                    spec_result.add_game(&result);
+                    samples.push(result.turns, result.time);
+
+                    on_game(
+                        &result,
+                        BatchProgress {
+                            spec_index,
+                            spec_played: spec_result.played,
+                            spec_total: spec.count,
+                            played,
+                            total,
+                        },
+                    );
+
+                    spec_result.info.push(result.info);
+                }
+
+                // A round cut short by cancellation leaves the spec unfinished
+                if round_played {
+                    let (low, high) = wilson_interval(spec_result.wins, spec_result.played, Z_95);
+
+                    finished = spec_result.played == spec.count || high - low <= precision.ci_width;
+                }
+            }
+
+            if !finished {
+                break;
+            }
+
+            total -= spec.count - spec_result.played;
+            spec_result.stats = samples.stats(spec_result.wins, spec_result.played);
+            on_spec(spec_result);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::game_batch::{CancelToken, GameBatch, Precision};
+    use crate::server::native::NativeServer;
+
+    #[test]
+    fn test_run_adaptive() {
+        let precision = Precision {
+            ci_width: 0.2,
+            round_size: 10,
+        };
+        let run = |cancel_at: Option<usize>| {
+            let cancel = CancelToken::new();
+            let mut spec_results = Vec::new();
+
+            GameBatch {
+                count_per_spec: 500,
+                dims_range: vec![6..=6, 6..=6],
+                mines_range: vec![1, 8, 30],
+                autoclear: true,
+                metaseed: 3,
+                rng: Default::default(),
+            }
+            .into_config()
+            .run_adaptive(
+                precision,
+                |spec| NativeServer::new(spec, false),
+                |game| game.seed,
+                &cancel,
+                |_game, progress| {
+                    if Some(progress.played) == cancel_at {
+                        cancel.cancel();
+                    }
+                },
+                |spec_result| spec_results.push(spec_result),
+            )
+            .unwrap();
+
+            spec_results
+        };
+
+        let spec_results = run(None);
+        let played: Vec<usize> = spec_results.iter().map(|r| r.played).collect();
+
+        // Near-certain wins and losses need fewer games than a board closer to 50%
+        assert!(played[0] < played[1] && played[2] < played[1]);
+
+        for spec_result in spec_results.iter() {
+            let (low, high) = spec_result.stats.win_rate_ci;
+
+            assert_eq!(spec_result.played % precision.round_size, 0);
+            assert!(high - low <= precision.ci_width || spec_result.played == 500);
+        }
+
+        // Cancelled part-way through the second spec, which is left out
+        let cancelled = run(Some(played[0] + precision.round_size / 2));
+        assert_eq!(cancelled.len(), 1);
+        assert_eq!(cancelled[0].info, spec_results[0].info);
+
+        let again: Vec<Vec<u32>> = run(None).into_iter().map(|r| r.info).collect();
+        assert_eq!(
+            again,
+            spec_results.into_iter().map(|r| r.info).collect::<Vec<_>>()
+        );
+    }
+}
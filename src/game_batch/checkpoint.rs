@@ -0,0 +1,94 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+use crate::game_batch::{BatchConfig, SpecResult};
+use crate::GameError;
+
+/// Finished spec results for a batch, saved as JSON lines: the batch parameters first, then one
+/// line per spec in batch order. Lines are only ever appended, so a crash can at worst leave an
+/// incomplete last line, which is dropped when the checkpoint is next opened. A bad line anywhere
+/// else is an error, rather than losing the results after it.
+pub struct Checkpoint {
+    file: File,
+}
+
+impl Checkpoint {
+    /// Opens the checkpoint at `path`, creating it if needed, and returns the spec results
+    /// already saved for `batch`.
+    pub fn open<I: Serialize + DeserializeOwned>(
+        path: &Path,
+        batch: &BatchConfig,
+    ) -> Result<(Self, Vec<SpecResult<I>>), GameError> {
+        let mut finished = Vec::new();
+
+        if path.exists() {
+            let mut lines = BufReader::new(File::open(path)?).lines();
+
+            if let Some(header) = lines.next() {
+                let saved: BatchConfig = serde_json::from_str(&header?)?;
+
+                if saved != *batch {
+                    return Err(format!(
+                        "Checkpoint {} is for a different batch: {:?}",
+                        path.display(),
+                        saved
+                    ))?;
+                }
+
+                let lines: Vec<String> = lines.collect::<Result<_, _>>()?;
+
+                for (i, line) in lines.iter().enumerate() {
+                    match serde_json::from_str(line) {
+                        Ok(spec_result) => finished.push(spec_result),
+                        // Only the last line can have been cut short
+                        Err(_) if i + 1 == lines.len() => (),
+                        Err(e) => {
+                            return Err(format!(
+                                "Checkpoint {} has a bad result on line {}: {}",
+                                path.display(),
+                                i + 2,
+                                e
+                            ))?;
+                        }
+                    }
+                }
+            }
+        }
+
+        // Rewrite the checkpoint with only its complete lines, so that new results are appended
+        // straight after them. The rename means the old checkpoint is kept if this fails.
+        let mut tmp_name = path
+            .file_name()
+            .ok_or_else(|| format!("Checkpoint {} isn't a file", path.display()))?
+            .to_os_string();
+        tmp_name.push(".tmp");
+        let tmp_path = path.with_file_name(tmp_name);
+
+        {
+            let mut tmp = File::create(&tmp_path)?;
+            writeln!(tmp, "{}", serde_json::to_string(batch)?)?;
+
+            for spec_result in finished.iter() {
+                writeln!(tmp, "{}", serde_json::to_string(spec_result)?)?;
+            }
+
+            tmp.sync_all()?;
+        }
+
+        fs::rename(&tmp_path, path)?;
+
+        let file = OpenOptions::new().append(true).open(path)?;
+
+        Ok((Checkpoint { file }, finished))
+    }
+
+    pub fn record<I: Serialize>(&mut self, spec_result: &SpecResult<I>) -> Result<(), GameError> {
+        writeln!(self.file, "{}", serde_json::to_string(spec_result)?)?;
+        self.file.sync_data()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,272 @@
+use mersenne_twister::MT19937;
+use rand::{Rng, SeedableRng};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::client::ClientOptions;
+use crate::game_batch::stats::mcnemar_p_value;
+use crate::game_batch::{
+    play_chunk, BatchConfig, BatchProgress, CancelToken, GameResult, MinesSpec, STREAM_CHUNK_SIZE,
+};
+use crate::server::{GameServer, GameSpec};
+use crate::GameError;
+
+/// A way of playing a batch's games, for comparison with another.
+#[derive(Clone, Debug, Default)]
+pub struct Variant {
+    pub name: String,
+    pub options: ClientOptions,
+    /// Overrides the autoclear setting of every spec.
+    pub autoclear: Option<bool>,
+}
+
+/// Results for a spec whose games were each played by both variants of a comparison.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PairedResult {
+    pub name: Option<String>,
+    pub dims: Vec<usize>,
+    pub mines_spec: MinesSpec,
+    pub mines: usize,
+    pub played: usize,
+    pub a_wins: usize,
+    pub b_wins: usize,
+    /// Seeds of the games won by A but lost by B, in batch order.
+    pub a_only: Vec<u32>,
+    /// Seeds of the games won by B but lost by A, in batch order.
+    pub b_only: Vec<u32>,
+    /// Exact McNemar test of whether the variants are equally good, from the games only one of
+    /// them won. A small value means that the difference is unlikely to be chance.
+    pub p_value: f64,
+}
+
+impl BatchConfig {
+    /// Plays every game of the batch twice, once for each variant, with the same seed.
+    /// `on_spec` is called for each spec as soon as its games are finished; progress counts
+    /// pairs of games. Seeds are the same as for `run_streaming`. When cancelled, B still plays
+    /// the games of the current chunk which A has played, so that all of them are paired.
+    pub fn run_paired<G: GameServer>(
+        self,
+        a: &Variant,
+        b: &Variant,
+        new_game: impl Fn(GameSpec) -> Result<G, GameError> + Sync,
+        cancel: &CancelToken,
+        mut on_progress: impl FnMut(BatchProgress),
+        mut on_spec: impl FnMut(PairedResult),
+    ) -> Result<(), GameError> {
+        let specs = self.specs()?;
+        let total = specs.iter().map(|spec| spec.count).sum();
+        let mut played = 0;
+
+        let BatchConfig { metaseed, rng, .. } = self;
+        let mut metaseed_rng: MT19937 = SeedableRng::from_seed(metaseed);
+
+        for (spec_index, spec) in specs.iter().enumerate() {
+            let mut paired_result = PairedResult {
+                name: spec.name.clone(),
+                dims: spec.dims.clone(),
+                mines_spec: spec.mines_spec,
+                mines: spec.mines,
+                played: 0,
+                a_wins: 0,
+                b_wins: 0,
+                a_only: Vec::new(),
+                b_only: Vec::new(),
+                p_value: 1.0,
+            };
+
+            while !cancel.is_cancelled() && paired_result.played < spec.count {
+                let chunk_size = STREAM_CHUNK_SIZE.min(spec.count - paired_result.played);
+                let seeds: Vec<u32> = (0..chunk_size).map(|_| metaseed_rng.next_u32()).collect();
+
+                // Games are tagged with their position in the chunk in place of their spec, so
+                // that the variants' results can be paired up when cancellation leaves gaps
+                let play = |variant: &Variant,
+                            positions: &[usize],
+                            cancel: &CancelToken|
+                 -> Result<Vec<Option<GameResult<()>>>, GameError> {
+                    let chunk = positions
+                        .iter()
+                        .map(|&position| {
+                            let game_spec = GameSpec {
+                                dims: spec.dims.clone(),
+                                mines: spec.mines,
+                                seed: seeds[position],
+                                autoclear: variant.autoclear.unwrap_or(spec.autoclear),
+                                rng,
+                            };
+
+                            (position, game_spec)
+                        })
+                        .collect();
+                    let mut by_position: Vec<_> = seeds.iter().map(|_| None).collect();
+
+                    for result in
+                        play_chunk(chunk, &variant.options, &new_game, &|_game| (), cancel)
+                    {
+                        let result = result?;
+                        let position = result.spec_index;
+
+                        by_position[position] = Some(result);
+                    }
+
+                    Ok(by_position)
+                };
+
+                let all_positions: Vec<usize> = (0..chunk_size).collect();
+                let a_results = play(a, &all_positions, cancel)?;
+
+                // B plays every game that A did, even once cancelled, so that none of A's games
+                // are wasted
+                let a_positions: Vec<usize> = all_positions
+                    .into_iter()
+                    .filter(|&position| a_results[position].is_some())
+                    .collect();
+                let b_results = play(b, &a_positions, &CancelToken::new())?;
+
+                // A's chunk may have been cut short by cancellation; only games played by both
+                // variants are counted
+                for pair in a_results.into_iter().zip(b_results) {
+                    let (a_result, b_result) = match pair {
+                        (Some(a_result), Some(b_result)) => (a_result, b_result),
+                        _ => continue,
+                    };
+
+                    played += 1;
+                    paired_result.played += 1;
+
+                    match (a_result.win, b_result.win) {
+                        (true, true) => {
+                            paired_result.a_wins += 1;
+                            paired_result.b_wins += 1;
+                        }
+                        (true, false) => {
+                            paired_result.a_wins += 1;
+                            paired_result.a_only.push(a_result.seed);
+                        }
+                        (false, true) => {
+                            paired_result.b_wins += 1;
+                            paired_result.b_only.push(b_result.seed);
+                        }
+                        (false, false) => (),
+                    }
+
+                    on_progress(BatchProgress {
+                        spec_index,
+                        spec_played: paired_result.played,
+                        spec_total: spec.count,
+                        played,
+                        total,
+                    });
+                }
+            }
+
+            if paired_result.played > 0 {
+                paired_result.p_value =
+                    mcnemar_p_value(paired_result.a_only.len(), paired_result.b_only.len());
+                on_spec(paired_result);
+            }
+
+            if cancel.is_cancelled() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::client::{ClientOptions, GuessStrategy, Opening};
+    use crate::game_batch::{CancelToken, GameBatch, Variant};
+    use crate::replay::Replay;
+    use crate::server::native::NativeServer;
+    use crate::server::{GameSpec, GameState};
+
+    #[test]
+    fn test_run_paired() {
+        let config = GameBatch {
+            count_per_spec: 40,
+            dims_range: vec![8..=8, 8..=8],
+            mines_range: vec![8, 14],
+            autoclear: true,
+            metaseed: 17,
+            rng: Default::default(),
+        }
+        .into_config();
+
+        let run = |a: &Variant, b: &Variant| {
+            let mut paired_results = Vec::new();
+
+            config
+                .clone()
+                .run_paired(
+                    a,
+                    b,
+                    |spec| NativeServer::new(spec, false),
+                    &CancelToken::new(),
+                    |_progress| (),
+                    |paired_result| paired_results.push(paired_result),
+                )
+                .unwrap();
+
+            paired_results
+        };
+
+        // A variant against itself always agrees
+        let default = Variant::default();
+
+        for paired_result in run(&default, &default) {
+            assert_eq!(paired_result.a_wins, paired_result.b_wins);
+            assert!(paired_result.a_only.is_empty() && paired_result.b_only.is_empty());
+            assert_eq!(paired_result.p_value, 1.0);
+        }
+
+        // The A side plays the same games as a plain run of the batch
+        let plain = config
+            .clone()
+            .run(|spec| NativeServer::new(spec, false), |_game| ())
+            .unwrap();
+        let other = Variant {
+            name: "corner".to_owned(),
+            options: ClientOptions {
+                guess: GuessStrategy::Random { seed: 2 },
+                opening: Opening::Corner,
+            },
+            autoclear: None,
+        };
+        let paired_results = run(&default, &other);
+
+        assert_eq!(paired_results.len(), plain.len());
+        assert!(paired_results
+            .iter()
+            .any(|r| !r.a_only.is_empty() && !r.b_only.is_empty()));
+
+        for (paired_result, spec_result) in paired_results.iter().zip(plain.iter()) {
+            assert_eq!(paired_result.a_wins, spec_result.wins);
+
+            // Each game that only one variant won is won by that variant's client, and lost by
+            // the other's, when played again from its seed
+            let wins = |variant: &Variant, seed| {
+                let spec = GameSpec {
+                    dims: paired_result.dims.clone(),
+                    mines: paired_result.mines,
+                    seed,
+                    autoclear: true,
+                    rng: Default::default(),
+                };
+                let server = NativeServer::new(spec, false).unwrap();
+                let replay = Replay::play(server, variant.options.clone()).unwrap();
+
+                replay.server.game_state == GameState::Win
+            };
+
+            for &seed in paired_result.a_only.iter() {
+                assert!(wins(&default, seed) && !wins(&other, seed));
+            }
+
+            for &seed in paired_result.b_only.iter() {
+                assert!(!wins(&default, seed) && wins(&other, seed));
+            }
+        }
+    }
+}
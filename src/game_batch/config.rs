@@ -0,0 +1,243 @@
+use itertools::{iproduct, Itertools};
+use serde_derive::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::game_batch::MinesSpec;
+use crate::server::BoardRng;
+use crate::GameError;
+
+/// A batch made up of blocks of specs, played in order. Unlike `GameBatch`, each spec can have
+/// its own game count and autoclear setting, and a name. Can be loaded from a TOML or JSON file,
+/// e.g.:
+///
+/// ```toml
+/// metaseed = 133337
+///
+/// [[blocks]]
+/// kind = "spec"
+/// name = "expert"
+/// dims = [30, 16]
+/// mines = 99
+/// count = 1000
+///
+/// [[blocks]]
+/// kind = "product"
+/// dims_range = [[10, 20], [10, 20]]
+/// mines_range = [0.1, 0.15, 0.2]
+/// count_per_spec = 100
+/// ```
+///
+/// TOML arrays can't mix types, so a `mines_range` there has either all counts or all densities.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BatchConfig {
+    pub metaseed: u32,
+    /// Generator for each game's board; the game seeds themselves always come from MT19937.
+    #[serde(default)]
+    pub rng: BoardRng,
+    pub blocks: Vec<SpecBlock>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum SpecBlock {
+    /// A single spec.
+    Spec {
+        #[serde(default)]
+        name: Option<String>,
+        dims: Vec<usize>,
+        mines: MinesSpec,
+        count: usize,
+        #[serde(default = "default_autoclear")]
+        autoclear: bool,
+    },
+    /// A spec for every combination of dimension sizes and mines, as for `GameBatch`. Boards
+    /// with too many mines are skipped. All of the specs share the block's name.
+    Product {
+        #[serde(default)]
+        name: Option<String>,
+        dims_range: Vec<Vec<usize>>,
+        mines_range: Vec<MinesSpec>,
+        count_per_spec: usize,
+        #[serde(default = "default_autoclear")]
+        autoclear: bool,
+    },
+}
+
+fn default_autoclear() -> bool {
+    true
+}
+
+/// A single spec of a batch, with its mine count worked out.
+#[derive(Clone, Debug)]
+pub struct SpecDef {
+    pub name: Option<String>,
+    pub dims: Vec<usize>,
+    pub mines_spec: MinesSpec,
+    pub mines: usize,
+    pub count: usize,
+    pub autoclear: bool,
+}
+
+impl BatchConfig {
+    /// Loads a config from a `.toml` or `.json` file.
+    pub fn load(path: &Path) -> Result<Self, GameError> {
+        let contents = fs::read_to_string(path)?;
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Ok(toml::from_str(&contents)?),
+            Some("json") => Ok(serde_json::from_str(&contents)?),
+            _ => Err(format!(
+                "Batch config {} should be a .toml or .json file",
+                path.display()
+            ))?,
+        }
+    }
+
+    /// Every spec of the batch, in order.
+    pub fn specs(&self) -> Result<Vec<SpecDef>, GameError> {
+        let mut specs = Vec::new();
+
+        for block in self.blocks.iter() {
+            match block {
+                SpecBlock::Spec {
+                    name,
+                    dims,
+                    mines,
+                    count,
+                    autoclear,
+                } => {
+                    if *count == 0 {
+                        Err(format!("Invalid spec: dims={:?} count=0", dims))?;
+                    }
+
+                    let size = dims.iter().product();
+                    let mine_count = match mines.mines(size) {
+                        Some(mine_count) if !dims.is_empty() => mine_count,
+                        _ => Err(format!("Invalid spec: dims={:?} mines={}", dims, mines))?,
+                    };
+
+                    specs.push(SpecDef {
+                        name: name.clone(),
+                        dims: dims.clone(),
+                        mines_spec: *mines,
+                        mines: mine_count,
+                        count: *count,
+                        autoclear: *autoclear,
+                    });
+                }
+                SpecBlock::Product {
+                    name,
+                    dims_range,
+                    mines_range,
+                    count_per_spec,
+                    autoclear,
+                } => {
+                    if *count_per_spec == 0 {
+                        Err(format!(
+                            "Invalid product: dims_range={:?} count_per_spec=0",
+                            dims_range
+                        ))?;
+                    }
+
+                    let all_dims = dims_range
+                        .iter()
+                        .map(|range| range.iter().cloned())
+                        .multi_cartesian_product();
+
+                    for (dims, &mines_spec) in iproduct!(all_dims, mines_range.iter()) {
+                        let size = dims.iter().product();
+
+                        if let Some(mines) = mines_spec.mines(size) {
+                            specs.push(SpecDef {
+                                name: name.clone(),
+                                dims,
+                                mines_spec,
+                                mines,
+                                count: *count_per_spec,
+                                autoclear: *autoclear,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(specs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::game_batch::{BatchConfig, MinesSpec};
+
+    #[test]
+    fn test_config() {
+        let config: BatchConfig = toml::from_str(
+            r#"
+            metaseed = 1
+
+            [[blocks]]
+            kind = "spec"
+            name = "expert"
+            dims = [30, 16]
+            mines = 99
+            count = 10
+
+            [[blocks]]
+            kind = "product"
+            dims_range = [[2, 10]]
+            mines_range = [0.5, 0.9]
+            count_per_spec = 5
+            autoclear = false
+            "#,
+        )
+        .unwrap();
+
+        let specs = config.specs().unwrap();
+        let summary: Vec<_> = specs
+            .iter()
+            .map(|spec| {
+                (
+                    spec.name.as_ref().map(String::as_str),
+                    spec.dims[0],
+                    spec.mines,
+                    spec.count,
+                )
+            })
+            .collect();
+
+        // 2 mines on a board of 2 is skipped
+        assert_eq!(
+            summary,
+            vec![
+                (Some("expert"), 30, 99, 10),
+                (None, 2, 1, 5),
+                (None, 10, 5, 5),
+                (None, 10, 9, 5),
+            ]
+        );
+        assert!(specs[0].autoclear && !specs[1].autoclear);
+        assert_eq!(specs[1].mines_spec, MinesSpec::Density(0.5));
+
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(serde_json::from_str::<BatchConfig>(&json).unwrap(), config);
+
+        let mut invalid = config.clone();
+        invalid.blocks.truncate(1);
+        invalid.blocks[0] =
+            toml::from_str("kind = 'spec'\ndims = [2]\nmines = 3\ncount = 1").unwrap();
+        assert!(invalid.specs().is_err());
+
+        // Blocks with no games are mistakes rather than empty specs
+        invalid.blocks[0] =
+            toml::from_str("kind = 'spec'\ndims = [2]\nmines = 1\ncount = 0").unwrap();
+        assert!(invalid.specs().is_err());
+
+        invalid.blocks[0] = toml::from_str(
+            "kind = 'product'\ndims_range = [[2, 3]]\nmines_range = [1]\ncount_per_spec = 0",
+        )
+        .unwrap();
+        assert!(invalid.specs().is_err());
+    }
+}
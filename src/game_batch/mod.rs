@@ -0,0 +1,932 @@
+mod adaptive;
+mod checkpoint;
+mod compare;
+mod config;
+mod shard;
+mod stats;
+
+use mersenne_twister::MT19937;
+use rand::{Rng, SeedableRng};
+#[cfg(feature = "rayon")]
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::iter::repeat;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::client::{Client, ClientOptions};
+use crate::game_batch::checkpoint::Checkpoint;
+use crate::game_batch::stats::SpecSamples;
+use crate::server::{BoardRng, GameServer, GameSpec, GameState};
+use crate::GameError;
+
+pub use self::adaptive::Precision;
+pub use self::compare::{PairedResult, Variant};
+pub use self::config::{BatchConfig, SpecBlock, SpecDef};
+pub use self::shard::{Shard, ShardBy, ShardResult, ShardSpec};
+pub use self::stats::{Distribution, SpecStats};
+
+// Number of games to play between handing results back in `run_streaming`. Large enough to keep
+// every core busy, but bounds how many games are held in memory at once.
+#[cfg(not(test))]
+const STREAM_CHUNK_SIZE: usize = 4096;
+#[cfg(test)]
+const STREAM_CHUNK_SIZE: usize = 16;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct GameBatch<D, M> {
+    pub count_per_spec: usize,
+    pub dims_range: Vec<D>,
+    pub mines_range: M,
+    pub autoclear: bool,
+    pub metaseed: u32,
+    /// Generator for each game's board; the game seeds themselves always come from MT19937.
+    #[serde(default)]
+    pub rng: BoardRng,
+}
+
+/// Number of mines on each board of a spec, given either directly or as a proportion of the
+/// board's cells. Serialized as a bare number, so that existing batches of counts still load.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MinesSpec {
+    Count(usize),
+    /// Proportion of cells, from 0 up to (but not including) 1.
+    Density(f64),
+}
+
+impl MinesSpec {
+    /// Number of mines for a board with `size` cells, or `None` if it can't be played.
+    pub fn mines(self, size: usize) -> Option<usize> {
+        let mines = match self {
+            MinesSpec::Count(mines) => mines,
+            MinesSpec::Density(density) if density >= 0.0 && density < 1.0 => {
+                (density * size as f64).round() as usize
+            }
+            MinesSpec::Density(_) => return None,
+        };
+
+        if mines < size {
+            Some(mines)
+        } else {
+            None
+        }
+    }
+}
+
+impl From<usize> for MinesSpec {
+    fn from(mines: usize) -> Self {
+        MinesSpec::Count(mines)
+    }
+}
+
+impl fmt::Display for MinesSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MinesSpec::Count(mines) => write!(f, "{}", mines),
+            // Rounded to hide floating point error from stepping through a range of densities
+            MinesSpec::Density(density) => write!(f, "{}%", (density * 1e8).round() / 1e6),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SpecResult<I> {
+    /// Name given to the spec in its batch config, if any.
+    #[serde(default)]
+    pub name: Option<String>,
+    pub dims: Vec<usize>,
+    /// Number of mines as given in the batch, which `mines` was worked out from. `None` for
+    /// results saved before densities were supported, which were all given as counts.
+    #[serde(default)]
+    pub mines_spec: Option<MinesSpec>,
+    pub mines: usize,
+    pub played: usize,
+    pub wins: usize,
+    /// Seeds of the spec's lost games, in batch order, to find them again with `Replay`.
+    #[serde(default)]
+    pub lost_seeds: Vec<u32>,
+    #[serde(default)]
+    pub stats: SpecStats,
+    pub info: Vec<I>,
+}
+
+impl<I> SpecResult<I> {
+    // Results for a spec before any of its games are played
+    fn new(spec: &SpecDef) -> Self {
+        SpecResult {
+            name: spec.name.clone(),
+            dims: spec.dims.clone(),
+            mines_spec: Some(spec.mines_spec),
+            mines: spec.mines,
+            played: 0,
+            wins: 0,
+            lost_seeds: Vec::new(),
+            stats: SpecStats::default(),
+            info: Vec::new(),
+        }
+    }
+
+    // Counts a game towards the results; its info is added separately
+    fn add_game(&mut self, result: &GameResult<I>) {
+        self.played += 1;
+
+        if result.win {
+            self.wins += 1;
+        } else {
+            self.lost_seeds.push(result.seed);
+        }
+    }
+}
+
+/// `js_serializable` implementation for `SpecResult<()>`. Used by webapp, but must be defined in
+/// the same module as `SpecResult`.
+#[cfg(feature = "webapp")]
+mod empty_spec_result_js_impl {
+    use super::SpecResult;
+    use stdweb::{
+        __js_serializable_boilerplate, __js_serializable_serde_boilerplate, js_serializable,
+    };
+
+    type EmptySpecResult = SpecResult<()>;
+
+    js_serializable!(EmptySpecResult);
+}
+
+// A spec with games still being played: its index, results so far and per-game measurements
+type OpenSpec<I> = (usize, SpecResult<I>, SpecSamples);
+
+/// Result of a single game in a batch, as passed to `run_streaming`'s per-game callback.
+pub struct GameResult<I> {
+    /// Position of the game's spec in the batch, in the order that spec results are produced.
+    pub spec_index: usize,
+    pub seed: u32,
+    pub win: bool,
+    /// Number of turns the client took.
+    pub turns: usize,
+    /// Time taken to set up and play the game, where there's a clock to measure it with.
+    pub time: Option<Duration>,
+    pub info: I,
+}
+
+/// How far through a batch `run_streaming` is, as of a just-finished game.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct BatchProgress {
+    pub spec_index: usize,
+    /// Games played so far for the finished game's spec.
+    pub spec_played: usize,
+    pub spec_total: usize,
+    /// Games played so far in the whole batch.
+    pub played: usize,
+    pub total: usize,
+}
+
+/// Stops a running batch from starting any more games. Games already being played are finished,
+/// and results are returned for every spec with at least one game played.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+impl<D, M> GameBatch<D, M>
+where
+    D: IntoIterator<Item = usize>,
+    M: IntoIterator,
+    <M as IntoIterator>::Item: Into<MinesSpec>,
+{
+    pub fn run<G: GameServer, I: Send>(
+        self,
+        new_game: impl Fn(GameSpec) -> Result<G, GameError> + Sync,
+        use_game_result: impl Fn(G) -> I + Sync,
+    ) -> Result<Vec<SpecResult<I>>, GameError> {
+        self.into_config().run(new_game, use_game_result)
+    }
+
+    /// See `BatchConfig::run_streaming`.
+    pub fn run_streaming<G: GameServer, I: Send>(
+        self,
+        new_game: impl Fn(GameSpec) -> Result<G, GameError> + Sync,
+        use_game_result: impl Fn(G) -> I + Sync,
+        cancel: &CancelToken,
+        on_game: impl FnMut(&GameResult<I>, BatchProgress),
+        on_spec: impl FnMut(SpecResult<I>),
+    ) -> Result<(), GameError> {
+        self.into_config()
+            .run_streaming(new_game, use_game_result, cancel, on_game, on_spec)
+    }
+
+    /// See `BatchConfig::run_checkpointed`.
+    pub fn run_checkpointed<G: GameServer, I: Send + serde::Serialize + DeserializeOwned>(
+        self,
+        path: &Path,
+        new_game: impl Fn(GameSpec) -> Result<G, GameError> + Sync,
+        use_game_result: impl Fn(G) -> I + Sync,
+        cancel: &CancelToken,
+        on_game: impl FnMut(&GameResult<I>, BatchProgress),
+        on_spec: impl FnMut(SpecResult<I>),
+    ) -> Result<(), GameError> {
+        self.into_config().run_checkpointed(
+            path,
+            new_game,
+            use_game_result,
+            cancel,
+            on_game,
+            on_spec,
+        )
+    }
+
+    /// The same batch as a config with a single block.
+    pub fn into_config(self) -> BatchConfig {
+        let GameBatch {
+            count_per_spec,
+            dims_range,
+            mines_range,
+            autoclear,
+            metaseed,
+            rng,
+        } = self.into_serializable();
+
+        BatchConfig {
+            metaseed,
+            rng,
+            blocks: vec![SpecBlock::Product {
+                name: None,
+                dims_range,
+                mines_range,
+                count_per_spec,
+                autoclear,
+            }],
+        }
+    }
+
+    pub fn into_serializable(self) -> GameBatch<Vec<usize>, Vec<MinesSpec>> {
+        let GameBatch {
+            count_per_spec,
+            dims_range,
+            mines_range,
+            autoclear,
+            metaseed,
+            rng,
+        } = self;
+        let dims_range = dims_range
+            .into_iter()
+            .map(|i| i.into_iter().collect())
+            .collect();
+        let mines_range = mines_range.into_iter().map(Into::into).collect();
+
+        GameBatch {
+            count_per_spec,
+            dims_range,
+            mines_range,
+            autoclear,
+            metaseed,
+            rng,
+        }
+    }
+}
+
+impl BatchConfig {
+    pub fn run<G: GameServer, I: Send>(
+        self,
+        new_game: impl Fn(GameSpec) -> Result<G, GameError> + Sync,
+        use_game_result: impl Fn(G) -> I + Sync,
+    ) -> Result<Vec<SpecResult<I>>, GameError> {
+        let mut spec_results = Vec::new();
+
+        self.run_streaming(
+            new_game,
+            use_game_result,
+            &CancelToken::new(),
+            |_game_result, _progress| (),
+            |spec_result| spec_results.push(spec_result),
+        )?;
+
+        Ok(spec_results)
+    }
+
+    /// Plays the batch a chunk of games at a time, calling `on_game` for each game and
+    /// `on_spec` for each spec as soon as all of their games are finished. Both are called in
+    /// batch order, however the games themselves are scheduled. Once `cancel` is cancelled, no
+    /// more games are started, and specs are passed to `on_spec` with the games played so far.
+    pub fn run_streaming<G: GameServer, I: Send>(
+        self,
+        new_game: impl Fn(GameSpec) -> Result<G, GameError> + Sync,
+        use_game_result: impl Fn(G) -> I + Sync,
+        cancel: &CancelToken,
+        on_game: impl FnMut(&GameResult<I>, BatchProgress),
+        mut on_spec: impl FnMut(SpecResult<I>),
+    ) -> Result<(), GameError> {
+        let specs = self.specs()?;
+        let total = specs.iter().map(|spec| spec.count).sum();
+
+        self.run_from(
+            specs,
+            0..total,
+            new_game,
+            use_game_result,
+            cancel,
+            on_game,
+            |_spec_index, spec_result, _samples, _complete| {
+                on_spec(spec_result);
+                Ok(())
+            },
+        )
+    }
+
+    /// As `run_streaming`, but saves each finished spec's results to a checkpoint file at
+    /// `path`. If the file already holds results for this batch, those specs are passed straight
+    /// to `on_spec` and the batch carries on from the first unfinished spec; the results are the
+    /// same as for an uninterrupted run.
+    pub fn run_checkpointed<G: GameServer, I: Send + serde::Serialize + DeserializeOwned>(
+        self,
+        path: &Path,
+        new_game: impl Fn(GameSpec) -> Result<G, GameError> + Sync,
+        use_game_result: impl Fn(G) -> I + Sync,
+        cancel: &CancelToken,
+        on_game: impl FnMut(&GameResult<I>, BatchProgress),
+        mut on_spec: impl FnMut(SpecResult<I>),
+    ) -> Result<(), GameError> {
+        let (mut checkpoint, finished) = Checkpoint::open(path, &self)?;
+        let specs = self.specs()?;
+        let skipped = specs[..finished.len()].iter().map(|spec| spec.count).sum();
+        let total = specs.iter().map(|spec| spec.count).sum();
+        let mut recording = true;
+        let mut next_spec = finished.len();
+
+        for spec_result in finished {
+            on_spec(spec_result);
+        }
+
+        self.run_from(
+            specs,
+            skipped..total,
+            new_game,
+            use_game_result,
+            cancel,
+            on_game,
+            |spec_index, spec_result, _samples, complete| {
+                // Specs cut short by cancellation are played again on resume. Later specs can
+                // still finish when games are played in parallel, but the checkpoint only holds
+                // results in batch order, so nothing after the first short spec is saved. Specs
+                // with no games played aren't passed here at all, hence the check on the index.
+                recording &= complete && spec_index == next_spec;
+                next_spec = spec_index + 1;
+
+                if recording {
+                    checkpoint.record(&spec_result)?;
+                }
+
+                on_spec(spec_result);
+                Ok(())
+            },
+        )
+    }
+
+    // Plays the games with batch positions in `game_range`, out of all the games of `specs`.
+    // `on_spec` is passed each spec's index, results and samples, and whether all of the spec's
+    // games were played. Progress is for the whole batch, with the games before the range
+    // counted as played.
+    #[allow(clippy::too_many_arguments)]
+    fn run_from<G: GameServer, I: Send>(
+        self,
+        specs: Vec<SpecDef>,
+        game_range: Range<usize>,
+        new_game: impl Fn(GameSpec) -> Result<G, GameError> + Sync,
+        use_game_result: impl Fn(G) -> I + Sync,
+        cancel: &CancelToken,
+        mut on_game: impl FnMut(&GameResult<I>, BatchProgress),
+        mut on_spec: impl FnMut(usize, SpecResult<I>, SpecSamples, bool) -> Result<(), GameError>,
+    ) -> Result<(), GameError> {
+        let total: usize = specs.iter().map(|spec| spec.count).sum();
+        let mut played = game_range.start;
+
+        let BatchConfig { metaseed, rng, .. } = self;
+        let mut metaseed_rng: MT19937 = SeedableRng::from_seed(metaseed);
+
+        let mut games = specs
+            .iter()
+            .enumerate()
+            .flat_map(|(spec_index, spec)| repeat(spec_index).take(spec.count))
+            .map(|spec_index| {
+                let spec = &specs[spec_index];
+                let seed = metaseed_rng.next_u32();
+
+                (
+                    spec_index,
+                    GameSpec {
+                        dims: spec.dims.clone(),
+                        mines: spec.mines,
+                        seed,
+                        autoclear: spec.autoclear,
+                        rng,
+                    },
+                )
+            })
+            .take(game_range.end);
+
+        // Seeds are drawn in order from the metaseed, so skipped games still have to be drawn
+        games.by_ref().take(game_range.start).for_each(drop);
+
+        // Specs cut short by cancellation may have no games at all
+        let mut finish_spec = |(spec_index, mut spec_result, samples): OpenSpec<I>| {
+            if spec_result.played > 0 {
+                let complete = spec_result.played == specs[spec_index].count;
+
+                spec_result.stats = samples.stats(spec_result.wins, spec_result.played);
+                on_spec(spec_index, spec_result, samples, complete)
+            } else {
+                Ok(())
+            }
+        };
+
+        // Specs with games in the current chunk, oldest first; all but the newest are finished
+        // once the chunk has been played.
+        let mut open_specs: VecDeque<OpenSpec<I>> = VecDeque::new();
+
+        while !cancel.is_cancelled() {
+            let chunk: Vec<(usize, GameSpec)> = games.by_ref().take(STREAM_CHUNK_SIZE).collect();
+
+            if chunk.is_empty() {
+                break;
+            }
+
+            // Games come in batch order, so each spec's games are together. Specs without games
+            // are never opened.
+            for &(i, _) in chunk.iter() {
+                if open_specs.back().map(|&(index, _, _)| index) != Some(i) {
+                    let spec = &specs[i];
+
+                    open_specs.push_back((i, SpecResult::new(spec), SpecSamples::default()));
+                }
+            }
+
+            let results = play_chunk(
+                chunk,
+                &ClientOptions::default(),
+                &new_game,
+                &use_game_result,
+                cancel,
+            );
+
+            for result in results {
+                let result = result?;
+                let (_, spec_result, samples) = open_specs
+                    .iter_mut()
+                    .find(|(index, _, _)| *index == result.spec_index)
+                    .unwrap();
+
+                played += 1;
+                spec_result.add_game(&result);
+                samples.push(result.turns, result.time);
+
+                on_game(
+                    &result,
+                    BatchProgress {
+                        spec_index: result.spec_index,
+                        spec_played: spec_result.played,
+                        spec_total: specs[result.spec_index].count,
+                        played,
+                        total,
+                    },
+                );
+
+                spec_result.info.push(result.info);
+            }
+
+            while open_specs.len() > 1 {
+                finish_spec(open_specs.pop_front().unwrap())?;
+            }
+        }
+
+        // The newest spec may continue into the next chunk, so is only finished at the end (or
+        // on cancellation)
+        for open_spec in open_specs {
+            finish_spec(open_spec)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Plays a chunk of games, in parallel where possible. Cancellation is checked before every game,
+// so a chunk in progress stops promptly too.
+fn play_chunk<G: GameServer, I: Send>(
+    chunk: Vec<(usize, GameSpec)>,
+    options: &ClientOptions,
+    new_game: &(impl Fn(GameSpec) -> Result<G, GameError> + Sync),
+    use_game_result: &(impl Fn(G) -> I + Sync),
+    cancel: &CancelToken,
+) -> Vec<Result<GameResult<I>, GameError>> {
+    #[cfg(feature = "rayon")]
+    let chunk_iter = chunk.into_par_iter();
+
+    #[cfg(not(feature = "rayon"))]
+    let chunk_iter = chunk.into_iter();
+
+    chunk_iter
+        .filter(|_| !cancel.is_cancelled())
+        .map(|(spec_index, spec)| {
+            let seed = spec.seed;
+
+            // There's no clock on wasm: `Instant::now` panics there
+            #[cfg(not(target_arch = "wasm32"))]
+            let start = Some(Instant::now());
+
+            #[cfg(target_arch = "wasm32")]
+            let start: Option<Instant> = None;
+
+            let mut game = new_game(spec)?;
+            let mut turns = 0;
+
+            {
+                let mut client = Client::with_options(&mut game, options.clone());
+
+                // The last turn, which ends the game, counts too
+                loop {
+                    turns += 1;
+
+                    if !client.turn()? {
+                        break;
+                    }
+                }
+            }
+
+            let time = start.map(|start| start.elapsed());
+            let win = game.game_state() == GameState::Win;
+            let info = use_game_result(game);
+
+            Ok(GameResult {
+                spec_index,
+                seed,
+                win,
+                turns,
+                time,
+                info,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+    #[cfg(feature = "rayon")]
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    #[cfg(feature = "rayon")]
+    use std::thread;
+    #[cfg(feature = "rayon")]
+    use std::time::Duration;
+    use std::{env, process};
+
+    #[cfg(feature = "rayon")]
+    use rayon::ThreadPoolBuilder;
+
+    use crate::game_batch::{CancelToken, GameBatch, MinesSpec, SpecResult, STREAM_CHUNK_SIZE};
+    use crate::server::native::NativeServer;
+
+    #[test]
+    fn test_run_streaming() {
+        // Enough games per spec that a spec straddles a chunk boundary
+        let count_per_spec = STREAM_CHUNK_SIZE / 2 + 1;
+        let batch = || GameBatch {
+            count_per_spec,
+            dims_range: vec![4..=4, 3..=5],
+            mines_range: 2..=2,
+            autoclear: true,
+            metaseed: 9,
+            rng: Default::default(),
+        };
+
+        let mut games = Vec::new();
+        let mut streamed = Vec::new();
+
+        batch()
+            .run_streaming(
+                |spec| NativeServer::new(spec, false),
+                |_game| (),
+                &CancelToken::new(),
+                |game, progress| {
+                    assert_eq!(progress.played, games.len() + 1);
+                    assert_eq!(progress.total, 3 * count_per_spec);
+                    games.push((game.spec_index, game.win));
+                },
+                |spec_result| streamed.push(spec_result),
+            )
+            .unwrap();
+
+        let collected = batch()
+            .run(|spec| NativeServer::new(spec, false), |_game| ())
+            .unwrap();
+
+        assert_eq!(streamed.len(), 3);
+        assert_eq!(games.len(), 3 * count_per_spec);
+        assert!(games.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+
+        for (i, (s, c)) in streamed.iter().zip(collected.iter()).enumerate() {
+            assert_eq!((&s.dims, s.mines, s.played), (&c.dims, c.mines, c.played));
+            assert_eq!(s.wins, c.wins);
+
+            let wins = games.iter().filter(|&&(index, win)| index == i && win);
+            assert_eq!(wins.count(), s.wins);
+        }
+    }
+
+    #[test]
+    fn test_run_cancel() {
+        let batch = GameBatch {
+            count_per_spec: STREAM_CHUNK_SIZE * 3 / 2,
+            dims_range: vec![4..=4, 3..=5],
+            mines_range: 2..=2,
+            autoclear: true,
+            metaseed: 9,
+            rng: Default::default(),
+        };
+        let cancel = CancelToken::new();
+        let mut spec_results = Vec::new();
+
+        batch
+            .run_streaming(
+                |spec| NativeServer::new(spec, false),
+                |_game| (),
+                &cancel,
+                |_game, progress| {
+                    if progress.played == STREAM_CHUNK_SIZE {
+                        cancel.cancel();
+                    }
+                },
+                |spec_result| spec_results.push(spec_result),
+            )
+            .unwrap();
+
+        // Cancelled after the first chunk, part-way through the first spec
+        assert_eq!(spec_results.len(), 1);
+        assert_eq!(spec_results[0].played, STREAM_CHUNK_SIZE);
+    }
+
+    #[test]
+    fn test_run_checkpointed() {
+        let batch = || GameBatch {
+            count_per_spec: 10,
+            dims_range: vec![4..=4, 3..=5],
+            mines_range: 2..=2,
+            autoclear: true,
+            metaseed: 9,
+            rng: Default::default(),
+        };
+        let path = env::temp_dir().join(format!("mines-rs-checkpoint-{}", process::id()));
+        let _ = fs::remove_file(&path);
+
+        let run_checkpointed = |cancel_at: Option<usize>| {
+            let cancel = CancelToken::new();
+            let mut spec_results = Vec::new();
+
+            batch()
+                .run_checkpointed(
+                    &path,
+                    |spec| NativeServer::new(spec, false),
+                    |_game| (),
+                    &cancel,
+                    |_game, progress| {
+                        if Some(progress.played) == cancel_at {
+                            cancel.cancel();
+                        }
+                    },
+                    |spec_result| spec_results.push(spec_result),
+                )
+                .unwrap();
+
+            spec_results
+        };
+
+        // Stopped after the first chunk, with the first spec finished and the second not
+        let partial = run_checkpointed(Some(STREAM_CHUNK_SIZE));
+        assert_eq!(partial.len(), 2);
+
+        // As if the process died part-way through saving a spec
+        OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"dims\":[4,")
+            .unwrap();
+
+        let uninterrupted = batch()
+            .run(|spec| NativeServer::new(spec, false), |_game| ())
+            .unwrap();
+        let assert_resumed = |resumed: Vec<SpecResult<()>>| {
+            assert_eq!(resumed.len(), uninterrupted.len());
+
+            for (r, u) in resumed.iter().zip(uninterrupted.iter()) {
+                assert_eq!(
+                    (&r.dims, r.mines, r.played, r.wins),
+                    (&u.dims, u.mines, u.played, u.wins)
+                );
+            }
+        };
+
+        assert_resumed(run_checkpointed(None));
+
+        // Stopped part-way through a chunk, as by another thread, once a game of the second spec
+        // has started. In parallel, games of the third spec can still be played to the end.
+        fs::remove_file(&path).unwrap();
+        let cancel = CancelToken::new();
+
+        batch()
+            .run_checkpointed(
+                &path,
+                |spec| {
+                    if spec.dims[1] == 4 {
+                        cancel.cancel();
+                    }
+
+                    NativeServer::new(spec, false)
+                },
+                |_game| (),
+                &cancel,
+                |_game, _progress| (),
+                |_spec_result: SpecResult<()>| (),
+            )
+            .unwrap();
+
+        // The batch parameters, and at most the first spec
+        assert!(fs::read_to_string(&path).unwrap().lines().count() <= 2);
+        assert_resumed(run_checkpointed(None));
+
+        // A checkpoint can't be resumed with different batch parameters
+        let other = GameBatch {
+            metaseed: 10,
+            ..batch()
+        };
+        let result = other.run_checkpointed(
+            &path,
+            |spec| NativeServer::new(spec, false),
+            |_game| (),
+            &CancelToken::new(),
+            |_game, _progress| (),
+            |_spec_result: SpecResult<()>| (),
+        );
+        assert!(result.is_err());
+
+        // A bad line before the last isn't taken for one cut short, so no results are lost
+        let saved = fs::read_to_string(&path).unwrap();
+        let mut lines: Vec<&str> = saved.lines().collect();
+        lines[1] = "{\"dims\":[4,";
+        fs::write(&path, lines.join("\n")).unwrap();
+
+        let result = batch().run_checkpointed(
+            &path,
+            |spec| NativeServer::new(spec, false),
+            |_game| (),
+            &CancelToken::new(),
+            |_game, _progress| (),
+            |_spec_result: SpecResult<()>| (),
+        );
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), lines.join("\n"));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_checkpoint_cancel_order() {
+        // One chunk of specs of two games each, on two threads: one plays the first specs while
+        // the other plays the last. The first spec's second game waits until a game of the last
+        // spec cancels the run, so the first spec finishes, the specs after it get no games, and
+        // the specs played by the other thread finish.
+        let batch = || GameBatch {
+            count_per_spec: 2,
+            dims_range: vec![4..=4, 3..=10],
+            mines_range: 2..=2,
+            autoclear: true,
+            metaseed: 5,
+            rng: Default::default(),
+        };
+        let path = env::temp_dir().join(format!("mines-rs-cancel-order-{}", process::id()));
+        let _ = fs::remove_file(&path);
+        let cancel = CancelToken::new();
+        let first_spec_games = AtomicUsize::new(0);
+        let pool = ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+
+        pool.install(|| {
+            batch().run_checkpointed(
+                &path,
+                |spec| {
+                    match spec.dims[1] {
+                        3 if first_spec_games.fetch_add(1, Ordering::SeqCst) == 1 => {
+                            // Waits for at most a few seconds, in case the games are scheduled
+                            // some other way
+                            for _ in 0..5000 {
+                                if cancel.is_cancelled() {
+                                    break;
+                                }
+
+                                thread::sleep(Duration::from_millis(1));
+                            }
+                        }
+                        10 => cancel.cancel(),
+                        _ => (),
+                    }
+
+                    NativeServer::new(spec, false)
+                },
+                |_game| (),
+                &cancel,
+                |_game, _progress| (),
+                |_spec_result: SpecResult<()>| (),
+            )
+        })
+        .unwrap();
+
+        let uninterrupted = batch()
+            .run(|spec| NativeServer::new(spec, false), |_game| ())
+            .unwrap();
+        let summary = |r: &SpecResult<()>| (r.dims.clone(), r.mines, r.played, r.wins);
+
+        // Whatever was saved is the start of the batch, in order
+        let saved = fs::read_to_string(&path).unwrap();
+
+        for (i, line) in saved.lines().skip(1).enumerate() {
+            let spec_result: SpecResult<()> = serde_json::from_str(line).unwrap();
+            assert_eq!(summary(&spec_result), summary(&uninterrupted[i]));
+        }
+
+        let mut resumed = Vec::new();
+
+        batch()
+            .run_checkpointed(
+                &path,
+                |spec| NativeServer::new(spec, false),
+                |_game| (),
+                &CancelToken::new(),
+                |_game, _progress| (),
+                |spec_result| resumed.push(spec_result),
+            )
+            .unwrap();
+
+        let resumed: Vec<_> = resumed.iter().map(summary).collect();
+        assert_eq!(
+            resumed,
+            uninterrupted.iter().map(summary).collect::<Vec<_>>()
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_mines_spec() {
+        assert_eq!(MinesSpec::Count(10).mines(100), Some(10));
+        assert_eq!(MinesSpec::Count(100).mines(100), None);
+        assert_eq!(MinesSpec::Density(0.15).mines(400), Some(60));
+        assert_eq!(MinesSpec::Density(0.999).mines(100), None);
+        assert_eq!(MinesSpec::Density(1.5).mines(100), None);
+
+        let json = "[10,0.15]";
+        let parsed: Vec<MinesSpec> = serde_json::from_str(json).unwrap();
+        assert_eq!(parsed, [MinesSpec::Count(10), MinesSpec::Density(0.15)]);
+        assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+
+        // Results saved before densities were supported still load
+        let old = r#"{"dims":[8,8],"mines":10,"played":1,"wins":1,"info":[null]}"#;
+        let parsed: SpecResult<()> = serde_json::from_str(old).unwrap();
+        assert_eq!((parsed.mines_spec, parsed.mines), (None, 10));
+
+        // The same density gives each board size its own mine count
+        let batch = GameBatch {
+            count_per_spec: 1,
+            dims_range: vec![10..=20, 10..=10],
+            mines_range: vec![MinesSpec::Density(0.2)],
+            autoclear: true,
+            metaseed: 9,
+            rng: Default::default(),
+        };
+        let results = batch
+            .run(|spec| NativeServer::new(spec, false), |_game| ())
+            .unwrap();
+        let mines: Vec<usize> = results.iter().map(|r| r.mines).collect();
+
+        assert_eq!(mines, (10..=20).map(|d| d * 2).collect::<Vec<_>>());
+        assert!(results
+            .iter()
+            .all(|r| r.mines_spec == Some(MinesSpec::Density(0.2))));
+    }
+}
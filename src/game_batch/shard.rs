@@ -0,0 +1,276 @@
+use serde_derive::{Deserialize, Serialize};
+
+use crate::game_batch::stats::SpecSamples;
+use crate::game_batch::{BatchConfig, BatchProgress, CancelToken, GameResult, SpecResult};
+use crate::server::{GameServer, GameSpec};
+use crate::GameError;
+
+/// One of `count` parts of a batch, numbered from 0. Each shard is a contiguous run of the
+/// batch's games, so shards can be played in separate processes and merged back in order.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Shard {
+    pub index: usize,
+    pub count: usize,
+    pub by: ShardBy,
+}
+
+/// How a batch is divided between shards.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShardBy {
+    /// Equal numbers of games; a spec may be split between shards.
+    Game,
+    /// Equal numbers of whole specs.
+    Spec,
+}
+
+impl Default for ShardBy {
+    fn default() -> Self {
+        ShardBy::Game
+    }
+}
+
+/// A shard's results, to be merged with the rest of the batch's shards.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShardResult<I> {
+    pub config: BatchConfig,
+    pub shard: Shard,
+    pub specs: Vec<ShardSpec<I>>,
+}
+
+/// Results for the part of a spec played in a shard.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShardSpec<I> {
+    /// Position of the spec in the batch.
+    pub spec_index: usize,
+    pub result: SpecResult<I>,
+    // Kept so that the merged spec's stats can be worked out from all of its games
+    samples: SpecSamples,
+}
+
+impl BatchConfig {
+    /// Plays only the games in `shard`, as `run_streaming` does. Progress is for the whole
+    /// batch, with the games of earlier shards counted as played.
+    pub fn run_shard<G: GameServer, I: Send>(
+        self,
+        shard: Shard,
+        new_game: impl Fn(GameSpec) -> Result<G, GameError> + Sync,
+        use_game_result: impl Fn(G) -> I + Sync,
+        cancel: &CancelToken,
+        on_game: impl FnMut(&GameResult<I>, BatchProgress),
+    ) -> Result<ShardResult<I>, GameError> {
+        if shard.index >= shard.count {
+            Err(format!("Invalid shard: {} of {}", shard.index, shard.count))?;
+        }
+
+        let specs = self.specs()?;
+        let game_index = |spec_index: usize| -> usize {
+            specs[..spec_index].iter().map(|spec| spec.count).sum()
+        };
+        let bound = |shard_index: usize| match shard.by {
+            ShardBy::Game => game_index(specs.len()) * shard_index / shard.count,
+            ShardBy::Spec => game_index(specs.len() * shard_index / shard.count),
+        };
+        let game_range = bound(shard.index)..bound(shard.index + 1);
+
+        let config = self.clone();
+        let mut shard_specs = Vec::new();
+
+        self.run_from(
+            specs,
+            game_range,
+            new_game,
+            use_game_result,
+            cancel,
+            on_game,
+            |spec_index, result, samples, _complete| {
+                shard_specs.push(ShardSpec {
+                    spec_index,
+                    result,
+                    samples,
+                });
+                Ok(())
+            },
+        )?;
+
+        Ok(ShardResult {
+            config,
+            shard,
+            specs: shard_specs,
+        })
+    }
+}
+
+impl<I> ShardResult<I> {
+    /// Merges the results of every shard of a batch, in any order, into the spec results of
+    /// the whole batch. These are the same as from a single run of the batch, apart from game
+    /// times.
+    pub fn merge(mut shards: Vec<ShardResult<I>>) -> Result<Vec<SpecResult<I>>, GameError> {
+        shards.sort_by_key(|shard_result| shard_result.shard.index);
+
+        let (config, shard) = match shards.first() {
+            Some(first) => (first.config.clone(), first.shard),
+            None => Err("No shard results to merge")?,
+        };
+
+        let all_shards = shards.iter().enumerate().all(|(i, shard_result)| {
+            shard_result.shard == Shard { index: i, ..shard } && shard_result.config == config
+        });
+
+        if !all_shards || shards.len() != shard.count {
+            Err(format!(
+                "Shard results should be for shards 0 to {} of one batch",
+                shard.count - 1
+            ))?;
+        }
+
+        let specs = config.specs()?;
+        let mut merged: Vec<(usize, SpecResult<I>, SpecSamples)> = Vec::new();
+
+        // Shards are contiguous, so a spec split between shards is at the end of one and the
+        // start of the next
+        for shard_spec in shards
+            .into_iter()
+            .flat_map(|shard_result| shard_result.specs)
+        {
+            let ShardSpec {
+                spec_index,
+                result,
+                samples,
+            } = shard_spec;
+
+            match merged.last_mut() {
+                Some((index, spec_result, spec_samples)) if *index == spec_index => {
+                    spec_result.played += result.played;
+                    spec_result.wins += result.wins;
+                    spec_result.lost_seeds.extend(result.lost_seeds);
+                    spec_result.info.extend(result.info);
+                    spec_samples.append(samples);
+                }
+                _ => merged.push((spec_index, result, samples)),
+            }
+        }
+
+        merged
+            .into_iter()
+            .map(|(spec_index, mut spec_result, samples)| {
+                if spec_result.played != specs[spec_index].count {
+                    Err(format!(
+                        "Spec {} has {} of {} games played; shards must be run to completion",
+                        spec_index, spec_result.played, specs[spec_index].count
+                    ))?;
+                }
+
+                spec_result.stats = samples.stats(spec_result.wins, spec_result.played);
+
+                Ok(spec_result)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::game_batch::{
+        BatchConfig, CancelToken, GameBatch, Shard, ShardBy, ShardResult, SpecResult,
+        STREAM_CHUNK_SIZE,
+    };
+    use crate::server::native::NativeServer;
+
+    fn run_shards(config: &BatchConfig, count: usize, by: ShardBy) -> Vec<SpecResult<u32>> {
+        // Shards in reverse, as they may finish in any order
+        let shards = (0..count)
+            .rev()
+            .map(|index| {
+                config
+                    .clone()
+                    .run_shard(
+                        Shard { index, count, by },
+                        |spec| NativeServer::new(spec, false),
+                        |game| game.seed,
+                        &CancelToken::new(),
+                        |_game, _progress| (),
+                    )
+                    .unwrap()
+            })
+            .collect();
+
+        ShardResult::merge(shards).unwrap()
+    }
+
+    #[test]
+    fn test_shards() {
+        // Shards by game end part way through specs and chunks
+        let config = GameBatch {
+            count_per_spec: STREAM_CHUNK_SIZE + 5,
+            dims_range: vec![4..=4, 3..=6],
+            mines_range: 2..=3,
+            autoclear: true,
+            metaseed: 21,
+            rng: Default::default(),
+        }
+        .into_config();
+
+        let single = config
+            .clone()
+            .run(|spec| NativeServer::new(spec, false), |game| game.seed)
+            .unwrap();
+
+        for &(count, by) in [(3, ShardBy::Game), (5, ShardBy::Game), (3, ShardBy::Spec)].iter() {
+            let merged = run_shards(&config, count, by);
+
+            assert_eq!(merged.len(), single.len());
+
+            for (m, s) in merged.iter().zip(single.iter()) {
+                assert_eq!((&m.dims, m.mines, m.played), (&s.dims, s.mines, s.played));
+                assert_eq!(
+                    (m.wins, &m.lost_seeds, &m.info),
+                    (s.wins, &s.lost_seeds, &s.info)
+                );
+                assert_eq!(m.stats.turns, s.stats.turns);
+                assert_eq!(m.stats.win_rate_ci, s.stats.win_rate_ci);
+            }
+        }
+
+        // More shards than specs leaves some shards empty
+        assert_eq!(run_shards(&config, 11, ShardBy::Spec).len(), single.len());
+
+        let missing = vec![config
+            .clone()
+            .run_shard(
+                Shard {
+                    index: 0,
+                    count: 2,
+                    by: ShardBy::Game,
+                },
+                |spec| NativeServer::new(spec, false),
+                |game| game.seed,
+                &CancelToken::new(),
+                |_game, _progress| (),
+            )
+            .unwrap()];
+
+        assert!(ShardResult::merge(missing).is_err());
+
+        // Progress is for the whole batch, with the earlier shards' games counted as played
+        let total = single.iter().map(|s| s.played).sum();
+        let mut progresses = Vec::new();
+
+        config
+            .run_shard(
+                Shard {
+                    index: 1,
+                    count: 2,
+                    by: ShardBy::Game,
+                },
+                |spec| NativeServer::new(spec, false),
+                |game| game.seed,
+                &CancelToken::new(),
+                |_game, progress| progresses.push((progress.played, progress.total)),
+            )
+            .unwrap();
+
+        assert_eq!(progresses.first(), Some(&(total / 2 + 1, total)));
+        assert_eq!(progresses.last(), Some(&(total, total)));
+    }
+}
@@ -0,0 +1,162 @@
+use serde_derive::{Deserialize, Serialize};
+use std::time::Duration;
+
+// Normal quantile for a two-sided 95% interval
+pub const Z_95: f64 = 1.96;
+
+/// Summary statistics for a spec's games.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpecStats {
+    /// 95% Wilson score interval for the win rate, as proportions.
+    pub win_rate_ci: (f64, f64),
+    /// Turns taken per game.
+    pub turns: Distribution,
+    /// Seconds per game. `None` where games can't be timed, e.g. on wasm.
+    pub time: Option<Distribution>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Distribution {
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+/// Per-game measurements for a spec, gathered while its games are played.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SpecSamples {
+    turns: Vec<f64>,
+    times: Vec<f64>,
+    untimed: bool,
+}
+
+impl SpecSamples {
+    pub fn push(&mut self, turns: usize, time: Option<Duration>) {
+        self.turns.push(turns as f64);
+
+        match time {
+            Some(time) => self
+                .times
+                .push(time.as_secs() as f64 + f64::from(time.subsec_nanos()) / 1e9),
+            None => self.untimed = true,
+        }
+    }
+
+    /// Adds the samples of games played after this one's, e.g. by another shard.
+    pub fn append(&mut self, mut other: SpecSamples) {
+        self.turns.append(&mut other.turns);
+        self.times.append(&mut other.times);
+        self.untimed |= other.untimed;
+    }
+
+    pub fn stats(&self, wins: usize, played: usize) -> SpecStats {
+        SpecStats {
+            win_rate_ci: wilson_interval(wins, played, Z_95),
+            turns: Distribution::new(self.turns.clone()),
+            time: if self.untimed {
+                None
+            } else {
+                Some(Distribution::new(self.times.clone()))
+            },
+        }
+    }
+}
+
+impl Distribution {
+    fn new(mut samples: Vec<f64>) -> Self {
+        if samples.is_empty() {
+            return Distribution::default();
+        }
+
+        samples.sort_by(|a, b| a.partial_cmp(b).expect("NaN sample"));
+
+        // Nearest-rank percentile
+        let percentile = |p: f64| {
+            let rank = (p / 100.0 * samples.len() as f64).ceil() as usize;
+            samples[rank.max(1) - 1]
+        };
+
+        Distribution {
+            mean: samples.iter().sum::<f64>() / samples.len() as f64,
+            p50: percentile(50.0),
+            p90: percentile(90.0),
+            p99: percentile(99.0),
+            max: samples[samples.len() - 1],
+        }
+    }
+}
+
+/// Wilson score interval for a binomial proportion, which unlike the normal approximation stays
+/// within [0, 1] and behaves sensibly for small samples and rates near 0% or 100%.
+pub fn wilson_interval(successes: usize, trials: usize, z: f64) -> (f64, f64) {
+    if trials == 0 {
+        return (0.0, 1.0);
+    }
+
+    let n = trials as f64;
+    let p = successes as f64 / n;
+    let z2 = z * z;
+
+    let denom = 1.0 + z2 / n;
+    let centre = (p + z2 / (2.0 * n)) / denom;
+    let half_width = z * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt() / denom;
+
+    (
+        (centre - half_width).max(0.0),
+        (centre + half_width).min(1.0),
+    )
+}
+
+/// Two-sided p-value of the exact McNemar test, for paired games where `a_only` were won only by
+/// one side and `b_only` only by the other: the chance of a split at least this uneven if both
+/// sides were equally good.
+pub fn mcnemar_p_value(a_only: usize, b_only: usize) -> f64 {
+    let n = a_only + b_only;
+    let k_max = a_only.min(b_only);
+
+    // Binomial(n, 1/2) tail, summed in log space so that large n doesn't underflow early
+    let ln_half_n = n as f64 * 0.5f64.ln();
+    let mut ln_choose = 0.0;
+    let mut tail = 0.0;
+
+    for k in 0..=k_max {
+        tail += (ln_choose + ln_half_n).exp();
+        ln_choose += ((n - k) as f64).ln() - ((k + 1) as f64).ln();
+    }
+
+    (2.0 * tail).min(1.0)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::game_batch::stats::{mcnemar_p_value, wilson_interval, Distribution, Z_95};
+
+    #[test]
+    fn test_stats() {
+        let close = |(a, b): (f64, f64), (c, d): (f64, f64)| {
+            assert!((a - c).abs() < 1e-4 && (b - d).abs() < 1e-4, "{:?}", (a, b));
+        };
+
+        close(wilson_interval(50, 100, Z_95), (0.4038, 0.5962));
+        close(wilson_interval(0, 10, Z_95), (0.0, 0.2775));
+        close(wilson_interval(10, 10, Z_95), (0.7225, 1.0));
+        close(wilson_interval(0, 0, Z_95), (0.0, 1.0));
+
+        close(
+            (mcnemar_p_value(0, 5), mcnemar_p_value(9, 1)),
+            (0.0625, 0.02148),
+        );
+        close((mcnemar_p_value(4, 4), mcnemar_p_value(0, 0)), (1.0, 1.0));
+        assert!(mcnemar_p_value(400, 600) < 1e-9);
+
+        let dist = Distribution::new((1..=100).rev().map(f64::from).collect());
+
+        assert_eq!(dist.mean, 50.5);
+        assert_eq!(dist.p50, 50.0);
+        assert_eq!(dist.p90, 90.0);
+        assert_eq!(dist.p99, 99.0);
+        assert_eq!(dist.max, 100.0);
+    }
+}
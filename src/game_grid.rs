@@ -1,77 +1,123 @@
 use itertools::Itertools;
-use std::collections::HashSet;
-use std::convert::TryInto;
-use std::iter::repeat;
+use std::collections::HashMap;
 use std::ops::{Index, IndexMut};
+use std::slice;
 
 use crate::coords::Coords;
 use crate::util::index_pair;
 
-pub struct GameGrid<C>(Vec<Option<C>>);
+pub struct GameGrid<C> {
+    cells: Vec<C>,
+    layout: GridLayout,
+    // Creates the cell for an index, given its number of neighbours
+    new_cell: fn(usize, usize) -> C,
+}
 
-#[derive(Clone, Copy, Debug)]
+enum GridLayout {
+    Bounded(Neighbourhood),
+    Unbounded(UnboundedLayout),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum DimReg {
     Start,
     Mid,
     End,
 }
 
+const DIM_REGS: [DimReg; 3] = [DimReg::Start, DimReg::Mid, DimReg::End];
+
 impl<C> GameGrid<C> {
-    pub fn new<F: Fn(usize, HashSet<usize>) -> C>(dims: &[usize], get_cell: F) -> Self {
-        if dims.iter().any(|&d| d < 2) {
-            panic!("All grid dimensions must be >= 2");
+    pub fn new(dims: &[usize], new_cell: fn(usize, usize) -> C) -> Self {
+        let neighbourhood = Neighbourhood::new(dims);
+        let size = dims.iter().product();
+        let cells = (0..size)
+            .map(|index| new_cell(index, neighbourhood.neighbours(index).len()))
+            .collect();
+
+        GameGrid {
+            cells,
+            layout: GridLayout::Bounded(neighbourhood),
+            new_cell,
         }
+    }
 
-        let size = dims.iter().fold(1, |s, &i| s * i);
-        let mut cells = Vec::with_capacity(size);
-        cells.resize_default(size);
+    /// A grid with no fixed size, which grows as co-ordinates are referred to.
+    pub fn unbounded(dim_count: usize, new_cell: fn(usize, usize) -> C) -> Self {
+        GameGrid {
+            cells: Vec::new(),
+            layout: GridLayout::Unbounded(UnboundedLayout::new(dim_count)),
+            new_cell,
+        }
+    }
 
-        let cell_regions = repeat(
-            [DimReg::Start, DimReg::Mid, DimReg::End]
-                .iter()
-                .map(Clone::clone),
-        )
-        .take(dims.len())
-        .multi_cartesian_product();
+    /// Index of the cell at `coords`; an unbounded grid creates the cell if it doesn't exist.
+    pub fn index(&mut self, coords: &Coords) -> usize {
+        let GameGrid {
+            ref mut cells,
+            ref mut layout,
+            new_cell,
+        } = *self;
 
-        for cell_region in cell_regions {
-            let offsets = region_offsets(&cell_region, dims);
-            let region_coords = cell_region
-                .iter()
-                .zip(dims.iter())
-                .map(|(&r, &d)| match r {
-                    DimReg::Start => 0..1,
-                    DimReg::Mid => 1..d - 1,
-                    DimReg::End => d - 1..d,
-                })
-                .multi_cartesian_product();
+        match *layout {
+            GridLayout::Bounded(ref neighbourhood) => coords.to_index(&neighbourhood.dims),
+            GridLayout::Unbounded(ref mut layout) => layout.intern(coords, cells, new_cell),
+        }
+    }
 
-            for coords in region_coords {
-                let index = Coords(coords.clone()).to_index(dims);
-                let surr = offsets
-                    .iter()
-                    .map(|&o| {
-                        (index as isize + o)
-                            .try_into()
-                            .expect("Calculated negative cell index")
-                    })
-                    .collect();
-
-                cells[index] = Some(get_cell(index, surr));
+    /// Index of the cell at `coords`, if it exists.
+    pub fn find(&self, coords: &Coords) -> Option<usize> {
+        match self.layout {
+            GridLayout::Bounded(ref neighbourhood) => coords.try_to_index(&neighbourhood.dims).ok(),
+            GridLayout::Unbounded(ref layout) => layout.indices.get(coords).cloned(),
+        }
+    }
+
+    pub fn coords(&self, index: usize) -> Coords {
+        match self.layout {
+            GridLayout::Bounded(ref neighbourhood) => {
+                Coords::from_index(index, &neighbourhood.dims)
             }
+            GridLayout::Unbounded(ref layout) => layout.coords[index].clone(),
         }
+    }
+
+    pub fn cell_mut(&mut self, index: usize) -> (&mut C, Neighbours<'_>) {
+        self.load_neighbours(index);
 
-        GameGrid(cells)
+        (&mut self.cells[index], self.layout.neighbours(index))
     }
 
-    pub fn cell_pair(&mut self, ia: usize, ib: usize) -> (&mut C, &mut C) {
-        let (a, b) = index_pair(self.0.as_mut_slice(), ia, ib);
+    pub fn cell_pair(
+        &mut self,
+        ia: usize,
+        ib: usize,
+    ) -> ((&mut C, Neighbours<'_>), (&mut C, Neighbours<'_>)) {
+        self.load_neighbours(ia);
+        self.load_neighbours(ib);
 
-        (a.as_mut().unwrap(), b.as_mut().unwrap())
+        let (a, b) = index_pair(self.cells.as_mut_slice(), ia, ib);
+
+        (
+            (a, self.layout.neighbours(ia)),
+            (b, self.layout.neighbours(ib)),
+        )
     }
 
     pub fn iter(&self) -> impl Iterator<Item = &C> {
-        self.0.iter().map(|c| c.as_ref().unwrap())
+        self.cells.iter()
+    }
+
+    fn load_neighbours(&mut self, index: usize) {
+        let GameGrid {
+            ref mut cells,
+            ref mut layout,
+            new_cell,
+        } = *self;
+
+        if let GridLayout::Unbounded(ref mut layout) = *layout {
+            layout.load_neighbours(index, cells, new_cell);
+        }
     }
 }
 
@@ -79,22 +125,269 @@ impl<C> Index<usize> for GameGrid<C> {
     type Output = C;
 
     fn index(&self, index: usize) -> &Self::Output {
-        self.0[index].as_ref().unwrap()
+        &self.cells[index]
     }
 }
 
 impl<C> IndexMut<usize> for GameGrid<C> {
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        self.0[index].as_mut().unwrap()
+        &mut self.cells[index]
+    }
+}
+
+impl GridLayout {
+    // Neighbours of an unbounded grid's cell must already be loaded.
+    fn neighbours(&self, index: usize) -> Neighbours<'_> {
+        match *self {
+            GridLayout::Bounded(ref neighbourhood) => neighbourhood.neighbours(index),
+            GridLayout::Unbounded(ref layout) => Neighbours(NeighbourIndices::List(
+                layout.neighbours[index]
+                    .as_ref()
+                    .expect("Neighbours of unbounded grid cell not loaded"),
+            )),
+        }
+    }
+}
+
+// Cells of an unbounded grid are indexed in the order they're first referred to. A cell's
+// neighbours are only looked up (and created) once the cell itself is acted on.
+struct UnboundedLayout {
+    // Co-ordinate offsets of each neighbour
+    coord_offsets: Vec<Vec<isize>>,
+    indices: HashMap<Coords, usize>,
+    coords: Vec<Coords>,
+    // Sorted neighbour indices of each cell, once loaded
+    neighbours: Vec<Option<Vec<usize>>>,
+}
+
+impl UnboundedLayout {
+    fn new(dim_count: usize) -> Self {
+        let coord_offsets = (0..dim_count)
+            .map(|_| -1..=1)
+            .multi_cartesian_product()
+            .filter(|offs: &Vec<isize>| offs.iter().any(|&o| o != 0))
+            .collect();
+
+        UnboundedLayout {
+            coord_offsets,
+            indices: HashMap::new(),
+            coords: Vec::new(),
+            neighbours: Vec::new(),
+        }
+    }
+
+    fn intern<C>(
+        &mut self,
+        coords: &Coords,
+        cells: &mut Vec<C>,
+        new_cell: fn(usize, usize) -> C,
+    ) -> usize {
+        if let Some(&index) = self.indices.get(coords) {
+            return index;
+        }
+
+        let index = cells.len();
+        cells.push(new_cell(index, self.coord_offsets.len()));
+        self.indices.insert(coords.clone(), index);
+        self.coords.push(coords.clone());
+        self.neighbours.push(None);
+
+        index
+    }
+
+    fn load_neighbours<C>(
+        &mut self,
+        index: usize,
+        cells: &mut Vec<C>,
+        new_cell: fn(usize, usize) -> C,
+    ) {
+        if self.neighbours[index].is_some() {
+            return;
+        }
+
+        let mut surr = Vec::with_capacity(self.coord_offsets.len());
+
+        for i in 0..self.coord_offsets.len() {
+            // Co-ordinates can be past `isize::MAX` on 32-bit targets, so the offset is added as
+            // a two's complement `usize` rather than converting the co-ordinate to `isize`
+            let coords = Coords(
+                self.coords[index]
+                    .0
+                    .iter()
+                    .zip(self.coord_offsets[i].iter())
+                    .map(|(&c, &o)| c.wrapping_add(o as usize))
+                    .collect(),
+            );
+
+            surr.push(self.intern(&coords, cells, new_cell));
+        }
+
+        surr.sort();
+        self.neighbours[index] = Some(surr);
+    }
+}
+
+// Neighbour index offsets, shared between all cells which lie in the same region (start, middle
+// or end) of every dimension. Memory use depends only on the number of dimensions, not the
+// number of cells.
+pub struct Neighbourhood {
+    dims: Vec<usize>,
+    // Indexed by region number; see `Neighbourhood::region`.
+    region_offsets: Vec<Vec<isize>>,
+}
+
+impl Neighbourhood {
+    pub fn new(dims: &[usize]) -> Self {
+        if dims.iter().any(|&d| d < 1) {
+            panic!("All grid dimensions must be >= 1");
+        }
+
+        let region_offsets = (0..dims.len())
+            .map(|_| DIM_REGS.iter().cloned())
+            .multi_cartesian_product()
+            .map(|cell_region| {
+                // Cells of a size-2 dimension are all at its start or end, and the single cell of a
+                // size-1 dimension is classed as its start
+                let possible = cell_region.iter().zip(dims.iter()).all(|(&r, &d)| match r {
+                    DimReg::Start => true,
+                    DimReg::Mid => d > 2,
+                    DimReg::End => d > 1,
+                });
+
+                if possible {
+                    region_offsets(&cell_region, dims)
+                } else {
+                    Vec::new()
+                }
+            })
+            .collect();
+
+        Neighbourhood {
+            dims: dims.to_vec(),
+            region_offsets,
+        }
+    }
+
+    pub fn neighbours(&self, index: usize) -> Neighbours<'_> {
+        Neighbours(NeighbourIndices::Offsets {
+            index,
+            offsets: &self.region_offsets[self.region(index)],
+        })
+    }
+
+    // Region numbers have a base-3 digit per dimension, in the same order as
+    // `multi_cartesian_product` produces regions.
+    fn region(&self, mut index: usize) -> usize {
+        let mut region = 0;
+        let mut radix = 1;
+
+        for &d in self.dims.iter().rev() {
+            let coord = index % d;
+            index /= d;
+
+            let dim_region = if coord == 0 {
+                DimReg::Start
+            } else if coord == d - 1 {
+                DimReg::End
+            } else {
+                DimReg::Mid
+            };
+
+            region += dim_region as usize * radix;
+            radix *= DIM_REGS.len();
+        }
+
+        region
+    }
+}
+
+/// Indices of the cells surrounding a single cell, in ascending order. A cell's neighbours can be
+/// referred to by slot, i.e. position in this order.
+#[derive(Clone, Copy, Debug)]
+pub struct Neighbours<'a>(NeighbourIndices<'a>);
+
+#[derive(Clone, Copy, Debug)]
+enum NeighbourIndices<'a> {
+    // Offsets from the cell's own index, shared with other cells
+    Offsets { index: usize, offsets: &'a [isize] },
+    List(&'a [usize]),
+}
+
+impl<'a> Neighbours<'a> {
+    pub fn len(&self) -> usize {
+        match self.0 {
+            NeighbourIndices::Offsets { offsets, .. } => offsets.len(),
+            NeighbourIndices::List(list) => list.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, slot: usize) -> usize {
+        match self.0 {
+            NeighbourIndices::Offsets { index, offsets } => {
+                (index as isize + offsets[slot]) as usize
+            }
+            NeighbourIndices::List(list) => list[slot],
+        }
+    }
+
+    pub fn slot(&self, other: usize) -> Option<usize> {
+        match self.0 {
+            NeighbourIndices::Offsets { index, offsets } => {
+                let offset = other as isize - index as isize;
+                offsets.binary_search(&offset).ok()
+            }
+            NeighbourIndices::List(list) => list.binary_search(&other).ok(),
+        }
+    }
+
+    pub fn iter(&self) -> NeighboursIter<'a> {
+        match self.0 {
+            NeighbourIndices::Offsets { index, offsets } => NeighboursIter::Offsets {
+                index,
+                offsets: offsets.iter(),
+            },
+            NeighbourIndices::List(list) => NeighboursIter::List(list.iter()),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum NeighboursIter<'a> {
+    Offsets {
+        index: usize,
+        offsets: slice::Iter<'a, isize>,
+    },
+    List(slice::Iter<'a, usize>),
+}
+
+impl<'a> Iterator for NeighboursIter<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        match *self {
+            NeighboursIter::Offsets {
+                index,
+                ref mut offsets,
+            } => {
+                let &offset = offsets.next()?;
+                Some((index as isize + offset) as usize)
+            }
+            NeighboursIter::List(ref mut list) => list.next().cloned(),
+        }
     }
 }
 
-// Does not account for dims where some dimension is of size 1; will produce
-// incorrect (overflowing) offsets in this case.
 fn region_offsets(cell_region: &[DimReg], dims: &[usize]) -> Vec<isize> {
-    cell_region
+    let mut offsets: Vec<isize> = cell_region
         .iter()
-        .map(|&dim_region| match dim_region {
+        .zip(dims.iter())
+        .map(|(&dim_region, &d)| match dim_region {
+            // A size-1 dimension has no neighbours along it
+            _ if d == 1 => 0..=0,
             DimReg::Start => 0..=1,
             DimReg::Mid => -1..=1,
             DimReg::End => -1..=0,
@@ -113,5 +406,57 @@ fn region_offsets(cell_region: &[DimReg], dims: &[usize]) -> Vec<isize> {
                 Some(index_offset)
             }
         })
-        .collect()
+        .collect();
+
+    offsets.sort();
+
+    offsets
+}
+
+#[cfg(test)]
+mod test {
+    use crate::coords::Coords;
+    use crate::game_grid::Neighbourhood;
+
+    #[test]
+    fn test_neighbours() {
+        for dims in &[
+            vec![3, 2, 4, 5],
+            vec![1, 6],
+            vec![4, 1, 3],
+            vec![1],
+            vec![1, 1, 2],
+        ] {
+            check_neighbours(dims);
+        }
+    }
+
+    fn check_neighbours(dims: &[usize]) {
+        let neighbourhood = Neighbourhood::new(dims);
+        let size: usize = dims.iter().product();
+
+        for index in 0..size {
+            let Coords(coords): Coords = Coords::from_index(index, dims);
+            let expected: Vec<usize> = (0..size)
+                .filter(|&other| {
+                    let Coords(other_coords): Coords = Coords::from_index(other, dims);
+
+                    other != index
+                        && coords
+                            .iter()
+                            .zip(other_coords.iter())
+                            .all(|(&a, &b)| a.max(b) - a.min(b) <= 1)
+                })
+                .collect();
+
+            let neighbours = neighbourhood.neighbours(index);
+
+            assert_eq!(neighbours.iter().collect::<Vec<_>>(), expected);
+
+            for (slot, &surr) in expected.iter().enumerate() {
+                assert_eq!(neighbours.get(slot), surr);
+                assert_eq!(neighbours.slot(surr), Some(slot));
+            }
+        }
+    }
 }
@@ -1,19 +1,38 @@
-#![feature(vec_resize_default)]
 #![feature(try_from)]
 
 use std::error::Error;
+mod analysis;
 mod client;
 mod coords;
 mod game_batch;
 mod game_grid;
 #[cfg(feature = "mongodb_connector")]
 pub mod mongodb_connector;
+mod race;
+mod render;
+mod replay;
 mod server;
 mod util;
 
-pub use crate::game_batch::{GameBatch, SpecResult};
+pub use crate::analysis::{BoardMetrics, RegionDensity};
+pub use crate::client::{Client, ClientOptions, GuessStrategy, Opening, SolverOverlay};
+pub use crate::coords::Coords;
+pub use crate::game_batch::{
+    BatchConfig, BatchProgress, CancelToken, Distribution, GameBatch, GameResult, MinesSpec,
+    PairedResult, Precision, Shard, ShardBy, ShardResult, ShardSpec, SpecBlock, SpecDef,
+    SpecResult, SpecStats, Variant,
+};
+pub use crate::race::{Race, RaceResult, RaceScore, Racer, RacerResult};
+pub use crate::render::{AnsiRenderer, CellGlyph, TextRenderer, Tiling};
+pub use crate::replay::{Replay, SolverStep};
+pub use crate::server::infinite::{InfiniteServer, InfiniteSpec};
 #[cfg(feature = "js_server_connector")]
-pub use crate::server::js::JsServerWrapper;
-pub use crate::server::native::NativeServer;
+pub use crate::server::js::{ApiHost, JsServerWrapper};
+pub use crate::server::native::{
+    ClusteredPlacer, ExplicitPlacer, GradientPlacer, Layout, MinePlacer, NativeServer,
+    UniformPlacer,
+};
+pub use crate::server::session::{Session, SessionClient};
+pub use crate::server::{BoardRng, GameServer, GameSpec, GameState, Pcg32};
 
 pub type GameError = Box<dyn Error + Sync + Send>;
@@ -80,7 +80,6 @@ impl Game {
         let &NativeServer {
             created_at,
             ref dims,
-            ref grid,
             mines,
             seed,
             autoclear,
@@ -88,20 +87,35 @@ impl Game {
             ..
         } = server;
 
-        let cell_array = grid
-            .iter()
-            .map(|&Cell { mine, action, .. }| match (mine, action) {
+        let cell_array = server
+            .cells()
+            .map(|Cell { mine, action, .. }| match (mine, action) {
                 (true, _) => CellState::Mine,
                 (false, CellAction::Cleared) => CellState::Cleared,
                 (false, _) => CellState::Empty,
             })
             .collect();
 
-        let flag_array = grid
-            .iter()
+        let flag_array = server
+            .cells()
             .map(|cell| cell.action == CellAction::Flagged)
             .collect();
 
+        // Turns taken without a client name are from the default client
+        let mut clients: Vec<String> = Vec::new();
+
+        for turn in native_turns.iter().flat_map(|turns| turns.iter().skip(1)) {
+            let client = turn.client.as_ref().map_or("RustoBusto", |c| c.as_str());
+
+            if !clients.iter().any(|c| c == client) {
+                clients.push(client.to_owned());
+            }
+        }
+
+        if clients.is_empty() {
+            clients.push("RustoBusto".to_owned());
+        }
+
         let turns = native_turns.as_ref().map(|native_turns| {
             native_turns
                 .iter()
@@ -119,7 +133,7 @@ impl Game {
             mines: mines as i32,
             autoclear,
             turns,
-            clients: vec!["RustoBusto".to_owned()],
+            clients,
             cell_array,
             flag_array,
         }
@@ -139,11 +153,11 @@ impl Turn {
             .clear_actual
             .iter()
             .map(|&i| {
-                let &Cell {
+                let Cell {
                     mine,
                     surr_mine_count,
                     ..
-                } = &server.grid[i];
+                } = server.cell(i);
 
                 let state = if mine {
                     CellState::Mine
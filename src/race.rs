@@ -0,0 +1,192 @@
+use std::cmp::Ordering;
+use std::time::{Duration, Instant};
+
+use crate::client::{Client, ClientOptions};
+use crate::server::native::NativeServer;
+use crate::server::{GameSpec, GameState};
+use crate::GameError;
+
+pub struct Racer {
+    pub name: String,
+    pub options: ClientOptions,
+}
+
+/// How finishers are ranked against each other. Racers which don't win are always ranked after
+/// those which do, by how many cells they cleared.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RaceScore {
+    Turns,
+    Time,
+}
+
+/// Several clients each playing their own copy of the same board, taking turns in rotation.
+pub struct Race {
+    pub spec: GameSpec,
+    pub racers: Vec<Racer>,
+}
+
+#[derive(Clone, Debug)]
+pub struct RacerResult {
+    pub name: String,
+    pub game_state: GameState,
+    pub turns: usize,
+    /// Time spent in the racer's own turns.
+    pub elapsed: Duration,
+    pub cleared: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct RaceResult {
+    /// Results in the same order as the race's racers.
+    pub results: Vec<RacerResult>,
+}
+
+impl Race {
+    pub fn run(self) -> Result<RaceResult, GameError> {
+        let Race { spec, racers } = self;
+
+        let mut servers = racers
+            .iter()
+            .map(|_| NativeServer::new(spec.clone(), false))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut results: Vec<RacerResult> = racers
+            .iter()
+            .map(|racer| RacerResult {
+                name: racer.name.clone(),
+                game_state: GameState::Ongoing,
+                turns: 0,
+                elapsed: Duration::from_secs(0),
+                cleared: 0,
+            })
+            .collect();
+
+        {
+            let mut clients: Vec<Option<Client<NativeServer>>> = servers
+                .iter_mut()
+                .zip(racers)
+                .map(|(server, racer)| Some(Client::with_options(server, racer.options)))
+                .collect();
+
+            // One turn for each racer still playing per round, so no racer gets ahead in turns
+            while clients.iter().any(Option::is_some) {
+                for (slot, result) in clients.iter_mut().zip(results.iter_mut()) {
+                    let playing = match slot {
+                        Some(client) => {
+                            let start = Instant::now();
+                            let playing = client.turn()?;
+
+                            result.elapsed += start.elapsed();
+                            result.turns += 1;
+
+                            playing
+                        }
+                        None => continue,
+                    };
+
+                    if !playing {
+                        *slot = None;
+                    }
+                }
+            }
+        }
+
+        for (server, result) in servers.iter().zip(results.iter_mut()) {
+            let size: usize = server.dims.iter().product();
+
+            result.game_state = server.game_state;
+            result.cleared = size - server.mines - server.cells_rem;
+        }
+
+        Ok(RaceResult { results })
+    }
+}
+
+impl RaceResult {
+    /// Orders two racers' results, best first.
+    pub fn head_to_head(a: &RacerResult, b: &RacerResult, score: RaceScore) -> Ordering {
+        let a_win = a.game_state == GameState::Win;
+        let b_win = b.game_state == GameState::Win;
+
+        match (a_win, b_win) {
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (true, true) => match score {
+                RaceScore::Turns => a.turns.cmp(&b.turns),
+                RaceScore::Time => a.elapsed.cmp(&b.elapsed),
+            },
+            (false, false) => b.cleared.cmp(&a.cleared),
+        }
+    }
+
+    /// All results, best first. Tied racers keep their starting order.
+    pub fn standings(&self, score: RaceScore) -> Vec<&RacerResult> {
+        let mut standings: Vec<&RacerResult> = self.results.iter().collect();
+        standings.sort_by(|a, b| Self::head_to_head(a, b, score));
+
+        standings
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cmp::Ordering;
+
+    use crate::client::{ClientOptions, GuessStrategy};
+    use crate::race::{Race, RaceResult, RaceScore, Racer};
+    use crate::server::{BoardRng, GameSpec, GameState};
+
+    #[test]
+    fn test_race() {
+        let racer = |name: &str, guess| Racer {
+            name: name.to_owned(),
+            options: ClientOptions {
+                guess,
+                ..Default::default()
+            },
+        };
+
+        let race = Race {
+            spec: GameSpec {
+                dims: vec![16, 16],
+                mines: 30,
+                seed: 8,
+                autoclear: true,
+                rng: BoardRng::default(),
+            },
+            racers: vec![
+                racer("first", GuessStrategy::First),
+                racer("random", GuessStrategy::Random { seed: 3 }),
+                racer("first_again", GuessStrategy::First),
+            ],
+        };
+        let result = race.run().unwrap();
+        let results = &result.results;
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[1].name, "random");
+
+        for r in results.iter() {
+            assert_ne!(r.game_state, GameState::Ongoing);
+            assert!(r.turns > 0);
+        }
+
+        // Same board and same strategy play out identically
+        assert_eq!(results[0].game_state, results[2].game_state);
+        assert_eq!(results[0].turns, results[2].turns);
+        assert_eq!(results[0].cleared, results[2].cleared);
+        assert_eq!(
+            RaceResult::head_to_head(&results[0], &results[2], RaceScore::Turns),
+            Ordering::Equal
+        );
+
+        let standings = result.standings(RaceScore::Turns);
+
+        for pair in standings.windows(2) {
+            assert_ne!(
+                RaceResult::head_to_head(pair[0], pair[1], RaceScore::Turns),
+                Ordering::Greater
+            );
+        }
+    }
+}
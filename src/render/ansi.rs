@@ -0,0 +1,130 @@
+use crate::client::SolverOverlay;
+use crate::coords::Coords;
+use crate::render::{CellGlyph, TextRenderer};
+use crate::server::native::NativeServer;
+use crate::server::GameState;
+
+const RESET: &str = "\x1b[0m";
+
+/// Renders a board with ANSI terminal colours, using the layout and characters of
+/// `TextRenderer`. An optional `SolverOverlay` highlights hidden cells which a client has
+/// deduced but not yet acted on: green for safe cells, magenta for mines.
+#[derive(Clone, Debug, Default)]
+pub struct AnsiRenderer {
+    pub text: TextRenderer,
+}
+
+impl AnsiRenderer {
+    pub fn render(&self, server: &NativeServer, overlay: Option<&SolverOverlay>) -> String {
+        self.text
+            .render_with(&server.dims, |index| self.cell_repr(server, overlay, index))
+    }
+
+    /// As `render`, but only the 2D slice of the board at `slice`, the co-ordinates of each
+    /// dimension after the second. Boards of up to two dimensions have a single, empty slice.
+    pub fn render_slice(
+        &self,
+        server: &NativeServer,
+        overlay: Option<&SolverOverlay>,
+        slice: &[usize],
+    ) -> String {
+        let dims = &server.dims;
+        let slice_dims = &dims[..dims.len() - slice.len()];
+
+        self.text.render_with(slice_dims, |slice_index| {
+            let mut coords: Coords = Coords::from_index(slice_index, slice_dims);
+            coords.0.extend(slice);
+
+            self.cell_repr(server, overlay, coords.to_index(dims))
+        })
+    }
+
+    fn cell_repr(
+        &self,
+        server: &NativeServer,
+        overlay: Option<&SolverOverlay>,
+        index: usize,
+    ) -> String {
+        let reveal_mines = self.text.reveal_mines || server.game_state == GameState::Lose;
+        let glyph = CellGlyph::new(&server.cell(index), reveal_mines);
+
+        let overlay_style = overlay.and_then(|overlay| match glyph {
+            CellGlyph::Hidden | CellGlyph::Mine if overlay.mines.contains(&index) => Some("30;45"),
+            CellGlyph::Hidden if overlay.safe.contains(&index) => Some("30;42"),
+            _ => None,
+        });
+
+        let style = overlay_style.unwrap_or_else(|| glyph_style(glyph));
+
+        format!("\x1b[{}m{}{}", style, glyph.to_char(), RESET)
+    }
+}
+
+// SGR parameters for each glyph; counts follow the classic minesweeper colours as closely as the
+// basic terminal palette allows.
+fn glyph_style(glyph: CellGlyph) -> &'static str {
+    match glyph {
+        CellGlyph::Hidden => "2",
+        CellGlyph::Flag => "1;31",
+        CellGlyph::MistakenFlag => "1;30;43",
+        CellGlyph::Mine => "1",
+        CellGlyph::LosingMine => "1;37;41",
+        CellGlyph::Count(0) => "0",
+        CellGlyph::Count(1) => "1;34",
+        CellGlyph::Count(2) => "32",
+        CellGlyph::Count(3) => "1;31",
+        CellGlyph::Count(4) => "34",
+        CellGlyph::Count(5) => "31",
+        CellGlyph::Count(6) => "36",
+        CellGlyph::Count(7) => "35",
+        CellGlyph::Count(8) => "90",
+        CellGlyph::Count(_) => "33",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::client::SolverOverlay;
+    use crate::coords::Coords;
+    use crate::render::AnsiRenderer;
+    use crate::server::native::{Layout, NativeServer};
+
+    #[test]
+    fn test_ansi_overlay() {
+        let layout = Layout::parse("*..\n...").unwrap();
+        let mut server = NativeServer::from_layout(&layout, false, false).unwrap();
+        server
+            .client_turn(None, vec![Coords(vec![1, 0])], vec![], vec![])
+            .unwrap();
+
+        // The mine at (0, 0) and a safe cell at (2, 1)
+        let overlay = SolverOverlay {
+            mines: vec![0].into_iter().collect(),
+            safe: vec![5].into_iter().collect(),
+        };
+        let cell = |style, c| format!("\x1b[{}m{}\x1b[0m", style, c);
+        let expected = format!(
+            "{} {} {}\n{} {} {}",
+            cell("30;45", '□'),
+            cell("1;34", '1'),
+            cell("2", '□'),
+            cell("2", '□'),
+            cell("2", '□'),
+            cell("30;42", '□')
+        );
+
+        assert_eq!(
+            AnsiRenderer::default().render(&server, Some(&overlay)),
+            expected
+        );
+
+        // Without the overlay, hidden cells are all dim
+        let plain = AnsiRenderer::default().render(&server, None);
+        assert_eq!(plain.matches(&cell("2", '□')).count(), 5);
+
+        assert_eq!(
+            AnsiRenderer::default().render_slice(&server, Some(&overlay), &[]),
+            expected
+        );
+    }
+}
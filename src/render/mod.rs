@@ -0,0 +1,260 @@
+mod ansi;
+
+use std::char;
+
+use crate::coords::Coords;
+use crate::server::native::{Cell, CellAction, NativeServer};
+use crate::server::GameState;
+
+pub use self::ansi::AnsiRenderer;
+
+/// Direction in which the 2D slices along some higher dimension are laid out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tiling {
+    Horizontal,
+    Vertical,
+}
+
+/// What a single cell is displayed as.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CellGlyph {
+    Hidden,
+    Flag,
+    /// A flag on a cell which isn't a mine; only shown once mines are revealed.
+    MistakenFlag,
+    /// An uncleared mine; only shown once mines are revealed.
+    Mine,
+    /// A cleared mine, i.e. the one which lost the game.
+    LosingMine,
+    Count(usize),
+}
+
+impl CellGlyph {
+    pub fn new(cell: &Cell, reveal_mines: bool) -> Self {
+        match (cell.action, cell.mine) {
+            (CellAction::Cleared, true) => CellGlyph::LosingMine,
+            (CellAction::Cleared, false) => CellGlyph::Count(cell.surr_mine_count),
+            (CellAction::Flagged, false) if reveal_mines => CellGlyph::MistakenFlag,
+            (CellAction::Flagged, _) => CellGlyph::Flag,
+            (CellAction::NoAction, true) if reveal_mines => CellGlyph::Mine,
+            (CellAction::NoAction, _) => CellGlyph::Hidden,
+        }
+    }
+
+    pub fn to_char(self) -> char {
+        match self {
+            CellGlyph::Hidden => '□',
+            CellGlyph::Flag => '⚐',
+            CellGlyph::MistakenFlag => '✗',
+            CellGlyph::Mine => '*',
+            CellGlyph::LosingMine => '☢',
+            CellGlyph::Count(0) => ' ',
+            // Boards of 3+ dimensions can have more than 9 surrounding mines
+            CellGlyph::Count(c) => char::from_digit(c as u32, 36).unwrap_or('+'),
+        }
+    }
+}
+
+/// Renders a board of any dimensionality as text. The first two dimensions form a 2D slice, with
+/// one row per co-ordinate of the second dimension; slices along each higher dimension are then
+/// tiled next to or below each other.
+#[derive(Clone, Debug, Default)]
+pub struct TextRenderer {
+    /// Tiling of each dimension after the second. Dimensions without an entry alternate between
+    /// horizontal and vertical, starting with horizontal.
+    pub tiling: Vec<Tiling>,
+    /// Show mines and mistaken flags while the game is still ongoing. They are always shown
+    /// once the game is lost.
+    pub reveal_mines: bool,
+}
+
+impl TextRenderer {
+    pub fn render(&self, server: &NativeServer) -> String {
+        let reveal_mines = self.reveal_mines || server.game_state == GameState::Lose;
+
+        self.render_with(&server.dims, |index| {
+            CellGlyph::new(&server.cell(index), reveal_mines)
+                .to_char()
+                .to_string()
+        })
+    }
+
+    /// Lays out the cells of a board with the given dims, where `cell_repr` gives the text for
+    /// each cell index. Each cell's text must display as a single character.
+    pub fn render_with<F: Fn(usize) -> String>(&self, dims: &[usize], cell_repr: F) -> String {
+        let mut coords = vec![0; dims.len()];
+
+        self.render_block(dims, dims.len(), &mut coords, &cell_repr)
+            .iter()
+            .map(|line| line.trim_end())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn tiling(&self, dim: usize) -> Tiling {
+        match self.tiling.get(dim - 2) {
+            Some(&tiling) => tiling,
+            None => [Tiling::Horizontal, Tiling::Vertical][dim % 2],
+        }
+    }
+
+    // Display width of a block of cells spanning the first `dim_count` dimensions.
+    fn block_width(&self, dims: &[usize], dim_count: usize) -> usize {
+        if dim_count <= 2 {
+            return dims[0] * 2 - 1;
+        }
+
+        let dim = dim_count - 1;
+        let inner_width = self.block_width(dims, dim);
+
+        match self.tiling(dim) {
+            Tiling::Horizontal => {
+                let separator_width = dim + 1;
+                dims[dim] * inner_width + (dims[dim] - 1) * separator_width
+            }
+            Tiling::Vertical => inner_width,
+        }
+    }
+
+    // Renders the block of cells spanning the first `dim_count` dimensions, at the co-ordinates
+    // already set in `coords` for all higher dimensions.
+    fn render_block<F: Fn(usize) -> String>(
+        &self,
+        dims: &[usize],
+        dim_count: usize,
+        coords: &mut [usize],
+        cell_repr: &F,
+    ) -> Vec<String> {
+        if dim_count <= 2 {
+            let row_count = if dim_count == 2 { dims[1] } else { 1 };
+
+            return (0..row_count)
+                .map(|y| {
+                    if dim_count == 2 {
+                        coords[1] = y;
+                    }
+
+                    (0..dims[0])
+                        .map(|x| {
+                            coords[0] = x;
+                            cell_repr(Coords(coords.to_vec()).to_index(dims))
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .collect();
+        }
+
+        let dim = dim_count - 1;
+        // Separators widen with each dimension so that nested slices stay distinguishable
+        let level = dim - 1;
+
+        let blocks: Vec<Vec<String>> = (0..dims[dim])
+            .map(|c| {
+                coords[dim] = c;
+                self.render_block(dims, dim, coords, cell_repr)
+            })
+            .collect();
+
+        match self.tiling(dim) {
+            Tiling::Horizontal => {
+                let separator = format!(" {} ", "│".repeat(level));
+
+                (0..blocks[0].len())
+                    .map(|row| {
+                        blocks
+                            .iter()
+                            .map(|block| block[row].as_str())
+                            .collect::<Vec<_>>()
+                            .join(&separator)
+                    })
+                    .collect()
+            }
+            Tiling::Vertical => {
+                // Pad separating lines to the block width, in case this block is tiled
+                // horizontally with others
+                let blank_line = " ".repeat(self.block_width(dims, dim));
+                let mut lines = Vec::new();
+
+                for (i, block) in blocks.into_iter().enumerate() {
+                    if i > 0 {
+                        lines.extend((0..level).map(|_| blank_line.clone()));
+                    }
+
+                    lines.extend(block);
+                }
+
+                lines
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::char;
+
+    use crate::coords::Coords;
+    use crate::render::{TextRenderer, Tiling};
+    use crate::server::native::{Layout, NativeServer};
+
+    // Renders each cell as its index, in base 36
+    fn render_indices(dims: &[usize], tiling: Vec<Tiling>) -> String {
+        let renderer = TextRenderer {
+            tiling,
+            reveal_mines: false,
+        };
+
+        renderer.render_with(dims, |index| {
+            char::from_digit(index as u32, 36).unwrap().to_string()
+        })
+    }
+
+    #[test]
+    fn test_render_tiling() {
+        use crate::render::Tiling::{Horizontal, Vertical};
+
+        assert_eq!(render_indices(&[3], vec![]), "0 1 2");
+        assert_eq!(render_indices(&[3, 2], vec![]), "0 2 4\n1 3 5");
+
+        assert_eq!(render_indices(&[2, 2, 2], vec![]), "0 4 │ 1 5\n2 6 │ 3 7");
+        assert_eq!(
+            render_indices(&[2, 2, 2], vec![Vertical]),
+            "0 4\n2 6\n\n1 5\n3 7"
+        );
+
+        // The third dimension is horizontal and the fourth vertical by default
+        assert_eq!(
+            render_indices(&[2, 2, 2, 2], vec![]),
+            "0 8 │ 2 a\n4 c │ 6 e\n\n\n1 9 │ 3 b\n5 d │ 7 f"
+        );
+        assert_eq!(
+            render_indices(&[2, 2, 2, 2], vec![Vertical, Horizontal]),
+            "0 8 ││ 1 9\n4 c ││ 5 d\n    ││\n2 a ││ 3 b\n6 e ││ 7 f"
+        );
+    }
+
+    #[test]
+    fn test_render_glyphs() {
+        let layout = Layout::parse("*..\n...").unwrap();
+        let mut server = NativeServer::from_layout(&layout, false, false).unwrap();
+        let hidden = TextRenderer::default();
+        let revealed = TextRenderer {
+            reveal_mines: true,
+            ..Default::default()
+        };
+
+        assert_eq!(hidden.render(&server), "□ □ □\n□ □ □");
+        assert_eq!(revealed.render(&server), "* □ □\n□ □ □");
+
+        // Flag a safe cell, and clear the mine's neighbour
+        let flag = vec![Coords(vec![0, 1])];
+        server.client_turn(None, vec![], flag, vec![]).unwrap();
+        server
+            .client_turn(None, vec![Coords(vec![1, 0])], vec![], vec![])
+            .unwrap();
+
+        assert_eq!(hidden.render(&server), "□ 1 □\n⚐ □ □");
+        assert_eq!(revealed.render(&server), "* 1 □\n✗ □ □");
+    }
+}
@@ -0,0 +1,97 @@
+use crate::client::{Client, ClientOptions, SolverOverlay};
+use crate::server::native::NativeServer;
+use crate::server::GameSpec;
+use crate::GameError;
+
+/// What the client had worked out going into one of its turns.
+#[derive(Clone, Debug)]
+pub struct SolverStep {
+    /// Whether the turn's clear was a guess rather than a deduction.
+    pub guess: bool,
+    /// Cells deduced to be safe or mines going into the turn. The turn flags the mines, and
+    /// clears the safe cells unless it's a guess, when `safe` is empty.
+    pub overlay: SolverOverlay,
+}
+
+/// A single game played again from its spec, e.g. a lost game from a batch, found by its dims,
+/// mines and seed.
+pub struct Replay {
+    /// The finished game. Its stored turns start with the initial state, so `turns[i + 1]` is
+    /// the turn taken after `solver[i]`.
+    pub server: NativeServer,
+    pub solver: Vec<SolverStep>,
+}
+
+impl Replay {
+    /// Plays the game with the default client, as a batch does.
+    pub fn run(spec: GameSpec) -> Result<Self, GameError> {
+        Self::play(NativeServer::new(spec, true)?, ClientOptions::default())
+    }
+
+    /// Plays a new game, e.g. one made from a `Layout`, with the given client options. Turns
+    /// are only in the replay if the server stores them.
+    pub fn play(mut server: NativeServer, options: ClientOptions) -> Result<Self, GameError> {
+        let mut solver = Vec::new();
+
+        {
+            let mut client = Client::with_options(&mut server, options);
+
+            // The client always has a cell to clear until the game is over, so every call takes
+            // a turn
+            loop {
+                solver.push(SolverStep {
+                    guess: client.guessing(),
+                    overlay: client.overlay(),
+                });
+
+                if !client.turn()? {
+                    break;
+                }
+            }
+        }
+
+        Ok(Replay { server, solver })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::game_batch::GameBatch;
+    use crate::replay::Replay;
+    use crate::server::native::NativeServer;
+    use crate::server::{GameSpec, GameState};
+
+    #[test]
+    fn test_replay_lost_game() {
+        let spec_results = GameBatch {
+            count_per_spec: 20,
+            dims_range: vec![8..=8, 8..=8],
+            mines_range: 12..=12,
+            autoclear: true,
+            metaseed: 5,
+            rng: Default::default(),
+        }
+        .run(|spec| NativeServer::new(spec, false), |_game| ())
+        .unwrap();
+
+        let spec_result = &spec_results[0];
+        assert_eq!(
+            spec_result.lost_seeds.len(),
+            spec_result.played - spec_result.wins
+        );
+
+        let replay = Replay::run(GameSpec {
+            dims: spec_result.dims.clone(),
+            mines: spec_result.mines,
+            seed: spec_result.lost_seeds[0],
+            autoclear: true,
+            rng: Default::default(),
+        })
+        .unwrap();
+        let turns = replay.server.turns.as_ref().unwrap();
+
+        assert_eq!(replay.server.game_state, GameState::Lose);
+        assert_eq!(turns.len(), replay.solver.len() + 1);
+        assert!(replay.solver[0].guess);
+    }
+}
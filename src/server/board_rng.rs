@@ -0,0 +1,163 @@
+use mersenne_twister::MT19937;
+use rand::{ChaChaRng, Rng, SeedableRng};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::server::native::MinePlacer;
+use crate::GameError;
+
+/// Random number generator used to lay out a board from its seed. Each variant pins the
+/// generator, how it's seeded and how mine placement draws from it; a variant's layouts must
+/// never change, so any change in behaviour needs a new variant.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum BoardRng {
+    /// Mersenne Twister, matching the JS server's layouts.
+    #[serde(rename = "mt19937-js-v1")]
+    Mt19937JsV1,
+    #[serde(rename = "pcg32-v1")]
+    Pcg32V1,
+    #[serde(rename = "chacha20-v1")]
+    ChaCha20V1,
+}
+
+impl Default for BoardRng {
+    fn default() -> Self {
+        BoardRng::Mt19937JsV1
+    }
+}
+
+impl BoardRng {
+    pub const ALL: [BoardRng; 3] = [
+        BoardRng::Mt19937JsV1,
+        BoardRng::Pcg32V1,
+        BoardRng::ChaCha20V1,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BoardRng::Mt19937JsV1 => "mt19937-js-v1",
+            BoardRng::Pcg32V1 => "pcg32-v1",
+            BoardRng::ChaCha20V1 => "chacha20-v1",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        BoardRng::ALL.iter().cloned().find(|rng| rng.name() == name)
+    }
+
+    pub fn place(
+        self,
+        placer: &impl MinePlacer,
+        dims: &[usize],
+        mines: usize,
+        seed: u32,
+    ) -> Result<Vec<bool>, GameError> {
+        match self {
+            BoardRng::Mt19937JsV1 => {
+                let mut rng: MT19937 = SeedableRng::from_seed(seed);
+                placer.place(dims, mines, &mut rng)
+            }
+            BoardRng::Pcg32V1 => placer.place(dims, mines, &mut Pcg32::from_seed(seed)),
+            BoardRng::ChaCha20V1 => {
+                let mut rng = ChaChaRng::from_seed(&[seed][..]);
+                placer.place(dims, mines, &mut rng)
+            }
+        }
+    }
+}
+
+const PCG_MULTIPLIER: u64 = 6_364_136_223_846_793_005;
+// Arbitrary fixed stream for seeding from a `u32`
+const PCG_DEFAULT_STREAM: u64 = 0x5851_f42d_4c95_7f2d;
+
+/// PCG32 (XSH-RR variant with 64-bit state), as in the PCG reference implementation.
+#[derive(Clone, Debug)]
+pub struct Pcg32 {
+    state: u64,
+    increment: u64,
+}
+
+impl Pcg32 {
+    pub fn new(seed: u64, stream: u64) -> Self {
+        let mut rng = Pcg32 {
+            state: 0,
+            increment: (stream << 1) | 1,
+        };
+
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+
+        rng
+    }
+}
+
+impl Rng for Pcg32 {
+    fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old
+            .wrapping_mul(PCG_MULTIPLIER)
+            .wrapping_add(self.increment);
+
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+}
+
+impl SeedableRng<u32> for Pcg32 {
+    fn reseed(&mut self, seed: u32) {
+        *self = Self::from_seed(seed);
+    }
+
+    fn from_seed(seed: u32) -> Self {
+        Pcg32::new(u64::from(seed), PCG_DEFAULT_STREAM)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::Rng;
+
+    use crate::server::board_rng::{BoardRng, Pcg32};
+    use crate::server::native::UniformPlacer;
+
+    #[test]
+    fn test_pcg32_reference() {
+        // Output of the PCG reference implementation's pcg32-demo, for its fixed seed
+        let mut rng = Pcg32::new(42, 54);
+        let expected = [
+            0xa15c_02b7,
+            0x7b47_f409,
+            0xba1d_3330,
+            0x83d2_f293,
+            0xbfa4_784b,
+        ];
+
+        for &e in expected.iter() {
+            assert_eq!(rng.next_u32(), e);
+        }
+    }
+
+    // Golden seed->layout vectors. If these fail, layouts for existing seeds have changed; add a
+    // new `BoardRng` variant instead of updating them.
+    #[test]
+    fn test_board_rng_layouts() {
+        let cases: [(BoardRng, [usize; 10]); 3] = [
+            (BoardRng::Mt19937JsV1, MT19937_JS_V1_LAYOUT),
+            (BoardRng::Pcg32V1, PCG32_V1_LAYOUT),
+            (BoardRng::ChaCha20V1, CHACHA20_V1_LAYOUT),
+        ];
+
+        for &(rng, expected) in cases.iter() {
+            let layout = rng.place(&UniformPlacer, &[8, 8], 10, 133_337).unwrap();
+            let mines: Vec<usize> = (0..layout.len()).filter(|&i| layout[i]).collect();
+
+            assert_eq!(mines, expected, "{}", rng.name());
+            assert_eq!(BoardRng::from_name(rng.name()), Some(rng));
+        }
+    }
+
+    // Mines placed by `UniformPlacer` on an 8x8 board with 10 mines and seed 133337
+    const MT19937_JS_V1_LAYOUT: [usize; 10] = [15, 16, 19, 31, 37, 39, 42, 56, 62, 63];
+    const PCG32_V1_LAYOUT: [usize; 10] = [2, 13, 17, 18, 26, 35, 36, 37, 52, 60];
+    const CHACHA20_V1_LAYOUT: [usize; 10] = [13, 19, 32, 34, 40, 42, 47, 49, 58, 60];
+}
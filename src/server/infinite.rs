@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+
+use crate::coords::Coords;
+use crate::server::native::UniformPlacer;
+use crate::server::{BoardRng, CellInfo, GameServer, GameState};
+use crate::util::BitVec;
+use crate::GameError;
+
+/// Width of a chunk along each dimension.
+pub const CHUNK_SIZE: usize = 16;
+
+// Co-ordinates are unsigned, so play starts far enough from zero that the board is effectively
+// unbounded in every direction.
+const ORIGIN: usize = 1 << 31;
+
+// How far along the first dimension to look for an empty start cell.
+const START_SEARCH: usize = 1024;
+
+#[derive(Clone, Debug)]
+pub struct InfiniteSpec {
+    pub dim_count: usize,
+    /// Proportion of each chunk's cells which are mines.
+    pub density: f64,
+    /// Number of cells to clear to win the game.
+    pub target: usize,
+    pub seed: u32,
+    pub autoclear: bool,
+    pub rng: BoardRng,
+}
+
+struct Chunk {
+    mines: BitVec,
+    cleared: BitVec,
+    flagged: BitVec,
+}
+
+/// A board with no edges. Mines are generated as each chunk of the board is first needed, from
+/// the game seed and the chunk co-ordinates, so a game is reproducible from its spec regardless
+/// of the order it's explored in. The game is won once `target` cells have been cleared.
+pub struct InfiniteServer {
+    spec: InfiniteSpec,
+    chunk_dims: Vec<usize>,
+    chunk_mines: usize,
+    chunks: HashMap<Vec<usize>, Chunk>,
+    // Stand-in dims for co-ordinate operations which need bounds
+    unbounded_dims: Vec<usize>,
+    start: Coords,
+    cleared: usize,
+    game_state: GameState,
+}
+
+impl InfiniteServer {
+    pub fn new(spec: InfiniteSpec) -> Result<Self, GameError> {
+        if spec.dim_count == 0
+            || spec.target == 0
+            || spec.density.is_nan()
+            || spec.density < 0.0
+            || spec.density >= 1.0
+        {
+            return Err(format!("Invalid infinite game params: {:?}", spec))?;
+        }
+
+        let chunk_dims = vec![CHUNK_SIZE; spec.dim_count];
+        let chunk_size: usize = chunk_dims.iter().product();
+        let chunk_mines = ((spec.density * chunk_size as f64).round() as usize).min(chunk_size - 1);
+
+        let mut server = InfiniteServer {
+            unbounded_dims: vec![std::usize::MAX; spec.dim_count],
+            start: Coords(vec![ORIGIN; spec.dim_count]),
+            spec,
+            chunk_dims,
+            chunk_mines,
+            chunks: HashMap::new(),
+            cleared: 0,
+            game_state: GameState::Ongoing,
+        };
+
+        // Start on an empty cell where possible, so that the first clear opens up some board
+        let dim_count = server.spec.dim_count;
+
+        if let Some(start) = (0..START_SEARCH)
+            .map(|x| {
+                let mut coords = vec![ORIGIN; dim_count];
+                coords[0] += x;
+                Coords(coords)
+            })
+            .find(|coords| !server.is_mine(coords) && server.surr_mine_count(coords) == 0)
+        {
+            server.start = start;
+        }
+
+        Ok(server)
+    }
+
+    fn chunk(&mut self, coords: &Coords) -> (&mut Chunk, usize) {
+        let InfiniteServer {
+            ref spec,
+            ref chunk_dims,
+            chunk_mines,
+            ref mut chunks,
+            ..
+        } = *self;
+
+        let chunk_coords: Vec<usize> = coords.0.iter().map(|&c| c / CHUNK_SIZE).collect();
+        let local_index =
+            Coords(coords.0.iter().map(|&c| c % CHUNK_SIZE).collect()).to_index(chunk_dims);
+
+        let seed = chunk_seed(spec.seed, &chunk_coords);
+
+        let chunk = chunks.entry(chunk_coords).or_insert_with(|| {
+            let mines = spec
+                .rng
+                .place(&UniformPlacer, chunk_dims, chunk_mines, seed)
+                .expect("Uniform mine placement failed");
+            let size = mines.len();
+
+            Chunk {
+                mines: BitVec::from(mines.as_slice()),
+                cleared: BitVec::new(size),
+                flagged: BitVec::new(size),
+            }
+        });
+
+        (chunk, local_index)
+    }
+
+    fn is_mine(&mut self, coords: &Coords) -> bool {
+        let (chunk, i) = self.chunk(coords);
+        chunk.mines.get(i)
+    }
+
+    fn surr_mine_count(&mut self, coords: &Coords) -> usize {
+        let surr: Vec<Coords> = coords.neighbours(&self.unbounded_dims).collect();
+        surr.iter().filter(|surr| self.is_mine(surr)).count()
+    }
+
+    // Clears a single cell, returning its info if it wasn't already cleared.
+    fn clear_cell(&mut self, coords: Coords) -> Option<CellInfo> {
+        let mine = {
+            let (chunk, i) = self.chunk(&coords);
+
+            if chunk.cleared.get(i) {
+                return None;
+            }
+
+            chunk.cleared.set(i, true);
+            chunk.flagged.set(i, false);
+            chunk.mines.get(i)
+        };
+
+        if mine {
+            self.game_state = GameState::Lose;
+        } else {
+            self.cleared += 1;
+
+            if self.cleared >= self.spec.target {
+                self.game_state = GameState::Win;
+            }
+        }
+
+        Some(CellInfo {
+            surrounding: self.surr_mine_count(&coords),
+            coords,
+            mine,
+        })
+    }
+
+    fn set_flag(&mut self, coords: &Coords, flag: bool) {
+        let (chunk, i) = self.chunk(coords);
+
+        if !chunk.cleared.get(i) {
+            chunk.flagged.set(i, flag);
+        }
+    }
+}
+
+impl GameServer for InfiniteServer {
+    fn turn(
+        &mut self,
+        clear: Vec<Coords>,
+        flag: Vec<Coords>,
+        unflag: Vec<Coords>,
+    ) -> Result<Vec<CellInfo>, GameError> {
+        if self.game_state != GameState::Ongoing {
+            return Err(String::from("Game already finished"))?;
+        }
+
+        if let Some(coords) = clear
+            .iter()
+            .chain(flag.iter())
+            .chain(unflag.iter())
+            .find(|coords| coords.0.len() != self.spec.dim_count)
+        {
+            return Err(format!(
+                "Co-ordinates {} don't match {} dimensions",
+                coords, self.spec.dim_count
+            ))?;
+        }
+
+        let mut clear_actual = Vec::new();
+        let mut to_cascade = Vec::new();
+
+        for coords in clear {
+            if let Some(info) = self.clear_cell(coords) {
+                if self.spec.autoclear && !info.mine && info.surrounding == 0 {
+                    to_cascade.push(info.coords.clone());
+                }
+
+                clear_actual.push(info);
+            }
+        }
+
+        // An opening may have no end, so stop once the game is won
+        while let Some(coords) = to_cascade.pop() {
+            if self.game_state != GameState::Ongoing {
+                break;
+            }
+
+            let surr: Vec<Coords> = coords.neighbours(&self.unbounded_dims).collect();
+
+            for surr in surr {
+                if let Some(info) = self.clear_cell(surr) {
+                    if info.surrounding == 0 {
+                        to_cascade.push(info.coords.clone());
+                    }
+
+                    clear_actual.push(info);
+                }
+            }
+        }
+
+        for coords in flag.iter() {
+            self.set_flag(coords, true);
+        }
+
+        for coords in unflag.iter() {
+            self.set_flag(coords, false);
+        }
+
+        Ok(clear_actual)
+    }
+
+    fn dims(&self) -> Option<&[usize]> {
+        None
+    }
+
+    fn dim_count(&self) -> usize {
+        self.spec.dim_count
+    }
+
+    fn start_coords(&self) -> Coords {
+        self.start.clone()
+    }
+
+    /// Number of mines in the chunks generated so far.
+    fn mines(&self) -> usize {
+        self.chunks.len() * self.chunk_mines
+    }
+
+    fn game_state(&self) -> GameState {
+        self.game_state
+    }
+
+    fn cells_rem(&self) -> usize {
+        self.spec.target.saturating_sub(self.cleared)
+    }
+}
+
+// Combines the game seed with chunk co-ordinates (using the SplitMix64 finaliser), so that every
+// chunk gets an independent mine layout.
+fn chunk_seed(seed: u32, chunk_coords: &[usize]) -> u32 {
+    let mix = |mut z: u64| {
+        z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    };
+
+    let hash = chunk_coords
+        .iter()
+        .fold(mix(u64::from(seed)), |h, &c| mix(h ^ c as u64));
+
+    (hash >> 32) as u32
+}
+
+#[cfg(test)]
+mod test {
+    use crate::client::Client;
+    use crate::coords::Coords;
+    use crate::server::infinite::{InfiniteServer, InfiniteSpec, CHUNK_SIZE};
+    use crate::server::{BoardRng, GameServer, GameState};
+
+    fn spec(seed: u32) -> InfiniteSpec {
+        InfiniteSpec {
+            dim_count: 2,
+            density: 0.1,
+            target: 2000,
+            seed,
+            autoclear: true,
+            rng: BoardRng::default(),
+        }
+    }
+
+    #[test]
+    fn test_infinite_chunks() {
+        let far = Coords(vec![1 << 20, 1 << 30]);
+        let far_mines = |server: &mut InfiniteServer| -> Vec<bool> {
+            (0..CHUNK_SIZE * CHUNK_SIZE)
+                .map(|i| {
+                    let Coords(offset): Coords = Coords::from_index(i, &[CHUNK_SIZE; 2]);
+                    let coords = far.0.iter().zip(offset.iter()).map(|(&c, &o)| c + o);
+                    server.is_mine(&Coords(coords.collect()))
+                })
+                .collect()
+        };
+
+        // Chunks don't depend on the order they're generated in: `a` plays a turn around the
+        // start before generating the far chunk, and `b` generates the far chunk first
+        let mut a = InfiniteServer::new(spec(5)).unwrap();
+        let mut b = InfiniteServer::new(spec(5)).unwrap();
+        let b_far = far_mines(&mut b);
+        let start = a.start_coords();
+
+        a.turn(vec![start], vec![], vec![]).unwrap();
+
+        assert!(b_far.contains(&true));
+        assert_eq!(far_mines(&mut a), b_far);
+        assert_eq!(a.start_coords(), b.start_coords());
+
+        // A different seed gives a different board
+        let mut c = InfiniteServer::new(spec(6)).unwrap();
+        assert_ne!(far_mines(&mut c), b_far);
+    }
+
+    #[test]
+    fn test_infinite_play() {
+        let mut server = InfiniteServer::new(spec(1)).unwrap();
+        Client::new(&mut server).play().unwrap();
+
+        assert_ne!(server.game_state(), GameState::Ongoing);
+        assert!(server
+            .turn(vec![server.start_coords()], vec![], vec![])
+            .is_err());
+    }
+}
@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::coords::Coords;
+use crate::server::js::api::req::{NewGameRequest, StatusRequest, TurnRequest};
+use crate::server::js::api::resp::{CellInfo, CellState, ServerResponse};
+use crate::server::native::NativeServer;
+use crate::server::{BoardRng, GameSpec, GameState};
+use crate::GameError;
+
+// Longest request read, headers included; API requests are far smaller
+const MAX_REQUEST_LEN: usize = 1 << 20;
+
+// How long a client can keep the host waiting for its request
+const READ_TIMEOUT_SECS: u64 = 10;
+
+// Largest game which a request can create, to keep one request from using up all the memory
+const MAX_DIM_COUNT: usize = 8;
+const MAX_CELLS: usize = 1 << 22;
+
+/// Hosts native games over HTTP, with the same JSON API as the node.js server, so that its
+/// clients (including `JsServerWrapper`) can play them. Requests are handled one at a time.
+/// Finished games are dropped once their last turn has been reported.
+#[derive(Default)]
+pub struct ApiHost {
+    games: HashMap<String, NativeServer>,
+    next_id: u64,
+}
+
+impl ApiHost {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serves requests to `<addr>/server/<action>` until the listener fails. Errors with a
+    /// single connection are reported to its client, or otherwise ignored.
+    pub fn serve(&mut self, addr: impl ToSocketAddrs) -> Result<(), GameError> {
+        let listener = TcpListener::bind(addr)?;
+
+        for stream in listener.incoming() {
+            let _ = self.handle_connection(stream?);
+        }
+
+        Ok(())
+    }
+
+    /// Handles the JSON body of a request to an action, `new`, `turn` or `status`, returning the
+    /// JSON response.
+    pub fn handle(&mut self, action: &str, body: &str) -> Result<String, GameError> {
+        let id = match action {
+            "new" => {
+                let request: NewGameRequest = serde_json::from_str(body)?;
+                let spec = GameSpec {
+                    dims: request.dims,
+                    mines: request.mines,
+                    seed: request.seed.unwrap_or_else(rand::random),
+                    autoclear: request.autoclear,
+                    rng: BoardRng::Mt19937JsV1,
+                };
+                let size = spec
+                    .dims
+                    .iter()
+                    .try_fold(1usize, |size, &dim| size.checked_mul(dim));
+                let size = match size {
+                    Some(size) if size <= MAX_CELLS && spec.dims.len() <= MAX_DIM_COUNT => size,
+                    _ => Err(format!(
+                        "Games can have at most {} dims and {} cells",
+                        MAX_DIM_COUNT, MAX_CELLS
+                    ))?,
+                };
+
+                // The native server panics on these rather than returning an error
+                if spec.dims.is_empty() || spec.mines >= size {
+                    Err(format!(
+                        "Invalid game: dims={:?} mines={}",
+                        spec.dims, spec.mines
+                    ))?;
+                }
+
+                let id = self.next_id.to_string();
+                self.next_id += 1;
+                self.games
+                    .insert(id.clone(), NativeServer::new(spec, true)?);
+
+                id
+            }
+            "turn" => {
+                let request: TurnRequest = serde_json::from_str(body)?;
+                let game = self.game(request.id)?;
+
+                game.client_turn(
+                    Some(request.client.to_owned()),
+                    request.clear,
+                    request.flag,
+                    request.unflag,
+                )?;
+
+                request.id.to_owned()
+            }
+            "status" => {
+                let request: StatusRequest = serde_json::from_str(body)?;
+                self.game(request.id)?;
+
+                request.id.to_owned()
+            }
+            _ => Err(format!("Unknown action `{}`", action))?,
+        };
+
+        let status = self.status(&id);
+
+        // A finished game takes no more turns, so there's no need to keep it
+        if status.game_over {
+            self.games.remove(&id);
+        }
+
+        Ok(serde_json::to_string(&status)?)
+    }
+
+    fn game(&mut self, id: &str) -> Result<&mut NativeServer, GameError> {
+        Ok(self
+            .games
+            .get_mut(id)
+            .ok_or_else(|| format!("No game with id `{}`", id))?)
+    }
+
+    // The game's state as of its latest turn
+    fn status(&self, id: &str) -> ServerResponse {
+        let game = &self.games[id];
+        let turns = game.turns.as_ref().expect("Hosted games store their turns");
+        let turn = turns
+            .last()
+            .expect("Games store their initial state as a turn");
+        let to_coords = |indices: &[usize]| {
+            indices
+                .iter()
+                .map(|&index| Coords::from_index(index, &game.dims))
+                .collect()
+        };
+
+        ServerResponse {
+            id: id.to_owned(),
+            seed: game.seed,
+            dims: game.dims.clone(),
+            mines: game.mines,
+            turn_num: turns.len() - 1,
+            game_over: game.game_state != GameState::Ongoing,
+            win: game.game_state == GameState::Win,
+            cells_rem: game.cells_rem,
+            flagged: to_coords(&turn.flagged),
+            unflagged: to_coords(&turn.unflagged),
+            clear_actual: turn
+                .clear_actual
+                .iter()
+                .map(|&index| {
+                    let cell = game.cell(index);
+
+                    CellInfo {
+                        surrounding: cell.surr_mine_count,
+                        state: if cell.mine {
+                            CellState::Mine
+                        } else {
+                            CellState::Cleared
+                        },
+                        coords: Coords::from_index(index, &game.dims),
+                    }
+                })
+                .collect(),
+            clear_req: to_coords(&turn.clear_req),
+            turn_taken_at: turn.timestamp,
+        }
+    }
+
+    fn handle_connection(&mut self, mut stream: TcpStream) -> Result<(), GameError> {
+        stream.set_read_timeout(Some(Duration::from_secs(READ_TIMEOUT_SECS)))?;
+
+        let mut reader = BufReader::new(stream.try_clone()?.take(MAX_REQUEST_LEN as u64));
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+
+        // Only the body length is needed from the headers
+        let mut content_length = 0;
+
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header)?;
+
+            let header = header.trim_end();
+
+            if header.is_empty() {
+                break;
+            }
+
+            let mut parts = header.splitn(2, ':');
+            let name = parts.next().unwrap_or("");
+
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = parts.next().unwrap_or("").trim().parse()?;
+            }
+        }
+
+        let mut parts = request_line.split_whitespace();
+        let response = match (parts.next(), parts.next()) {
+            _ if content_length > MAX_REQUEST_LEN => Err((
+                "413 Payload Too Large",
+                format!("Request bodies should be at most {} bytes", MAX_REQUEST_LEN),
+            )),
+            (Some("POST"), Some(path)) if path.starts_with("/server/") => {
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body)?;
+
+                let body = String::from_utf8(body)?;
+                self.handle(&path["/server/".len()..], &body)
+                    .map_err(|e| ("400 Bad Request", e.to_string()))
+            }
+            _ => Err(("404 Not Found", String::from("Not found"))),
+        };
+
+        let (status, content_type, body) = match response {
+            Ok(json) => ("200 OK", "application/json", json),
+            Err((status, message)) => (status, "text/plain", message),
+        };
+
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            content_type,
+            body.len(),
+            body
+        )?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::server::js::api::resp::{CellState, ServerResponse};
+    use crate::server::js::host::ApiHost;
+
+    #[test]
+    fn test_api_host() {
+        let mut host = ApiHost::new();
+        let new_game = r#"{"client":"test","seed":12,"dims":[4,4],"mines":2,"autoclear":true}"#;
+        let status: ServerResponse =
+            serde_json::from_str(&host.handle("new", new_game).unwrap()).unwrap();
+
+        assert_eq!((status.turn_num, status.cells_rem), (0, 14));
+
+        let turn = format!(
+            r#"{{"id":"{}","client":"test","clear":[[0,0]],"flag":[],"unflag":[]}}"#,
+            status.id
+        );
+        let status: ServerResponse =
+            serde_json::from_str(&host.handle("turn", &turn).unwrap()).unwrap();
+
+        assert_eq!(status.turn_num, 1);
+        assert_eq!(status.clear_req.len(), 1);
+
+        let cleared = status
+            .clear_actual
+            .iter()
+            .filter(|cell| cell.state == CellState::Cleared);
+        assert_eq!(status.cells_rem + cleared.count(), 14);
+
+        assert!(host.handle("status", r#"{"id":"nope"}"#).is_err());
+
+        // Finished games are dropped after their last turn
+        let new_game = r#"{"client":"test","seed":1,"dims":[2],"mines":1,"autoclear":true}"#;
+        let status: ServerResponse =
+            serde_json::from_str(&host.handle("new", new_game).unwrap()).unwrap();
+        let turn = format!(
+            r#"{{"id":"{}","client":"test","clear":[[0],[1]],"flag":[],"unflag":[]}}"#,
+            status.id
+        );
+        let status: ServerResponse =
+            serde_json::from_str(&host.handle("turn", &turn).unwrap()).unwrap();
+
+        assert!(status.game_over);
+        assert!(host
+            .handle("status", &format!(r#"{{"id":"{}"}}"#, status.id))
+            .is_err());
+        assert!(host
+            .handle(
+                "new",
+                r#"{"client":"test","dims":[2],"mines":2,"autoclear":true}"#
+            )
+            .is_err());
+
+        // Games too big to host
+        for dims in &[
+            "[1000000000,1000000000]",
+            "[4294967296,4294967296]",
+            "[2,2,2,2,2,2,2,2,2]",
+        ] {
+            let new_game = format!(
+                r#"{{"client":"test","dims":{},"mines":1,"autoclear":true}}"#,
+                dims
+            );
+            assert!(host.handle("new", &new_game).is_err());
+        }
+    }
+}
@@ -1,4 +1,5 @@
 mod api;
+mod host;
 
 use api::req::{JsServerRequest, NewGameRequest, TurnRequest};
 use api::resp::{CellInfo as JsCellInfo, CellState, ServerResponse};
@@ -8,9 +9,11 @@ use serde::ser::Serialize;
 use std::str;
 
 use crate::coords::Coords;
-use crate::server::{CellInfo as NativeCellInfo, GameServer, GameSpec, GameState};
+use crate::server::{BoardRng, CellInfo as NativeCellInfo, GameServer, GameSpec, GameState};
 use crate::GameError;
 
+pub use self::host::ApiHost;
+
 pub struct JsServerWrapper {
     client_name: String,
     status: ServerResponse,
@@ -25,8 +28,13 @@ impl JsServerWrapper {
             mines,
             seed,
             autoclear,
+            rng,
         }: GameSpec,
     ) -> Result<JsServerWrapper, GameError> {
+        if rng != BoardRng::Mt19937JsV1 {
+            return Err(format!("JS server doesn't support {} boards", rng.name()))?;
+        }
+
         let client_name = "RustyBoi";
         let http_client = Client::new();
         let base_url = "http://localhost:1066/server";
@@ -124,8 +132,12 @@ impl<'a> GameServer for JsServerWrapper {
         Ok(clear_actual_native)
     }
 
-    fn dims(&self) -> &[usize] {
-        &self.status.dims
+    fn dims(&self) -> Option<&[usize]> {
+        Some(&self.status.dims)
+    }
+
+    fn dim_count(&self) -> usize {
+        self.status.dims.len()
     }
 
     fn mines(&self) -> usize {
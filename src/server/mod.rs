@@ -1,16 +1,22 @@
+mod board_rng;
+pub mod infinite;
 #[cfg(feature = "js_server_connector")]
 pub mod js;
 pub mod native;
+pub mod session;
 
 use crate::coords::Coords;
 use crate::GameError;
 
-#[derive(Debug)]
+pub use self::board_rng::{BoardRng, Pcg32};
+
+#[derive(Clone, Debug)]
 pub struct GameSpec {
     pub dims: Vec<usize>,
     pub mines: usize,
     pub seed: u32,
     pub autoclear: bool,
+    pub rng: BoardRng,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -29,7 +35,20 @@ pub trait GameServer: Sized {
         unflag: Vec<Coords>,
     ) -> Result<Vec<CellInfo>, GameError>;
 
-    fn dims(&self) -> &[usize];
+    /// Size of each dimension, or `None` for an unbounded board.
+    fn dims(&self) -> Option<&[usize]>;
+
+    fn dim_count(&self) -> usize;
+
+    /// Co-ordinates for a client's first clear. Defaults to the centre of the board; unbounded
+    /// servers must provide their own.
+    fn start_coords(&self) -> Coords {
+        let dims = self
+            .dims()
+            .expect("Unbounded game server must provide start co-ordinates");
+
+        Coords(dims.iter().map(|&d| d / 2).collect())
+    }
 
     fn mines(&self) -> usize;
 
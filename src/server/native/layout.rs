@@ -0,0 +1,307 @@
+use std::fs;
+use std::path::Path;
+
+use crate::coords::Coords;
+use crate::server::native::{ExplicitPlacer, NativeServer};
+use crate::server::{BoardRng, GameSpec};
+use crate::GameError;
+
+const MINE_CHAR: char = '*';
+const EMPTY_CHAR: char = '.';
+const COMMENT_CHAR: char = '#';
+
+/// A fixed mine layout, read from a text format where `*` is a mine and `.` an empty cell. Each
+/// line is a row along the first dimension; consecutive rows run along the second. Layers of
+/// higher dimensions are separated by blank lines: one blank line between layers of the third
+/// dimension, two between blocks of the fourth, and so on. Whitespace within rows and lines
+/// starting with `#` are ignored.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layout {
+    pub dims: Vec<usize>,
+    pub mines: Vec<bool>,
+}
+
+impl Layout {
+    pub fn parse(text: &str) -> Result<Self, GameError> {
+        let mut rows: Vec<(usize, Vec<bool>)> = Vec::new();
+        let mut blank_run = 0;
+
+        for (line_num, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.starts_with(COMMENT_CHAR) {
+                continue;
+            }
+
+            if line.is_empty() {
+                blank_run += 1;
+                continue;
+            }
+
+            let row = line
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .map(|c| match c {
+                    MINE_CHAR => Ok(true),
+                    EMPTY_CHAR => Ok(false),
+                    _ => Err(format!(
+                        "Unexpected character {:?} in layout on line {}",
+                        c,
+                        line_num + 1
+                    )),
+                })
+                .collect::<Result<Vec<bool>, String>>()?;
+
+            // Blank lines before the first row don't separate anything
+            let separator = if rows.is_empty() { 0 } else { blank_run };
+            rows.push((separator, row));
+            blank_run = 0;
+        }
+
+        let width = match rows.first() {
+            Some((_, row)) => row.len(),
+            None => return Err("Layout contains no cells")?,
+        };
+
+        if let Some((_, row)) = rows.iter().find(|(_, row)| row.len() != width) {
+            return Err(format!(
+                "Layout rows must be equal length; found {} and {}",
+                width,
+                row.len()
+            ))?;
+        }
+
+        // A run of `n` blank lines advances the co-ordinate of dimension `n + 1`, resetting those
+        // of all lower dimensions.
+        let dim_count = rows.iter().map(|&(sep, _)| sep).max().unwrap_or(0) + 2;
+        let mut row_coords = vec![0; dim_count];
+        let mut dims = vec![1; dim_count];
+        dims[0] = width;
+
+        let mut positioned_rows = Vec::with_capacity(rows.len());
+
+        for (i, (separator, row)) in rows.into_iter().enumerate() {
+            if i > 0 {
+                row_coords[separator + 1] += 1;
+
+                for c in row_coords[1..=separator].iter_mut() {
+                    *c = 0;
+                }
+            }
+
+            for (d, &c) in dims.iter_mut().zip(row_coords.iter()).skip(1) {
+                *d = (*d).max(c + 1);
+            }
+
+            positioned_rows.push((row_coords.clone(), row));
+        }
+
+        // A single row is a 1-dimensional board
+        if dims[1] == 1 && dim_count == 2 {
+            dims.truncate(1);
+        }
+
+        let row_count: usize = dims[1..].iter().product();
+
+        if positioned_rows.len() != row_count {
+            return Err(format!(
+                "Layout layers are inconsistently sized; {} rows don't fill dims {:?}",
+                positioned_rows.len(),
+                dims
+            ))?;
+        }
+
+        let mut mines = vec![false; dims.iter().product()];
+
+        for (mut coords, row) in positioned_rows {
+            coords.truncate(dims.len());
+
+            for (x, mine) in row.into_iter().enumerate() {
+                coords[0] = x;
+                mines[Coords(coords.clone()).to_index(&dims)] = mine;
+            }
+        }
+
+        Ok(Layout { dims, mines })
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, GameError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn mine_count(&self) -> usize {
+        self.mines.iter().filter(|&&m| m).count()
+    }
+
+    /// Text representation of the layout, in the format accepted by `Layout::parse`.
+    pub fn repr(&self) -> String {
+        let dims = &self.dims;
+        let width = dims[0];
+        let row_count = self.mines.len() / width;
+
+        let rows = (0..row_count).map(|row_index| {
+            (0..width)
+                .map(|x| {
+                    // Rows run along the second dimension first, unlike cell indices
+                    let mut coords = vec![x];
+                    let mut rem = row_index;
+
+                    for &d in dims[1..].iter() {
+                        coords.push(rem % d);
+                        rem /= d;
+                    }
+
+                    if self.mines[Coords(coords).to_index(dims)] {
+                        MINE_CHAR
+                    } else {
+                        EMPTY_CHAR
+                    }
+                })
+                .collect::<String>()
+        });
+
+        let mut repr = String::new();
+
+        for (row_index, row) in rows.enumerate() {
+            if row_index > 0 {
+                // The number of higher dimensions which roll over at this row determines how
+                // many blank lines separate it from the last
+                let mut blank_lines = 0;
+                let mut stride = 1;
+
+                for &d in dims[1..].iter().take(dims.len().saturating_sub(2)) {
+                    stride *= d;
+
+                    if row_index % stride == 0 {
+                        blank_lines += 1;
+                    }
+                }
+
+                repr.push('\n');
+                repr.extend((0..blank_lines).map(|_| '\n'));
+            }
+
+            repr.push_str(&row);
+        }
+
+        repr
+    }
+}
+
+impl NativeServer {
+    /// Creates a game with exactly the given mine layout.
+    pub fn from_layout(
+        layout: &Layout,
+        autoclear: bool,
+        store_turns: bool,
+    ) -> Result<Self, GameError> {
+        let mines = layout.mine_count();
+
+        if mines >= layout.mines.len() {
+            return Err(format!(
+                "Layout must contain at least one empty cell; dims={:?}",
+                layout.dims
+            ))?;
+        }
+
+        let spec = GameSpec {
+            dims: layout.dims.clone(),
+            mines,
+            seed: 0,
+            autoclear,
+            rng: BoardRng::default(),
+        };
+
+        Self::with_placer(spec, store_turns, &ExplicitPlacer(layout.mines.clone()))
+    }
+
+    /// The mine layout of this game, e.g. to save as a regression board.
+    pub fn layout(&self) -> Layout {
+        Layout {
+            dims: self.dims.clone(),
+            mines: self.cells().map(|cell| cell.mine).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::coords::Coords;
+    use crate::server::native::layout::Layout;
+    use crate::server::native::NativeServer;
+
+    #[test]
+    fn test_layout_dims() {
+        let layout = Layout::parse(
+            "
+            # 3x2x2x2
+            *..
+            .*.
+
+            ..*
+            ...
+
+
+            ...
+            *..
+
+            .**
+            ...
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(layout.dims, vec![3, 2, 2, 2]);
+        assert_eq!(layout.mine_count(), 6);
+        assert!(layout.mines[0]);
+        assert!(layout.mines[Coords(vec![2, 0, 1, 0]).to_index(&layout.dims)]);
+        assert!(layout.mines[Coords(vec![0, 1, 0, 1]).to_index(&layout.dims)]);
+
+        let server = NativeServer::from_layout(&layout, true, false).unwrap();
+        assert_eq!(server.mines, 6);
+        assert_eq!(server.layout(), layout);
+    }
+
+    #[test]
+    fn test_layout_repr() {
+        for text in &[
+            "*.*.*",
+            "*..\n.*.",
+            "*.\n..\n\n.*\n*.\n\n..\n**",
+            "*.\n\n.*\n\n\n..\n\n.*",
+        ] {
+            let layout = Layout::parse(text).unwrap();
+
+            assert_eq!(&layout.repr(), text);
+            assert_eq!(Layout::parse(&layout.repr()).unwrap(), layout);
+        }
+    }
+
+    #[test]
+    fn test_layout_size_one_dims() {
+        // Same board as a 4x3 grid, with a size-1 dimension inserted
+        let flat = Layout::parse("*...\n..*.\n.*..").unwrap();
+        let layered = Layout::parse("*...\n\n..*.\n\n.*..").unwrap();
+        assert_eq!(layered.dims, vec![4, 1, 3]);
+
+        let flat_server = NativeServer::from_layout(&flat, true, false).unwrap();
+        let layered_server = NativeServer::from_layout(&layered, true, false).unwrap();
+
+        for (a, b) in flat_server.cells().zip(layered_server.cells()) {
+            assert_eq!(a.surr_mine_count, b.surr_mine_count);
+        }
+
+        let strip = Layout::parse("..*.*").unwrap();
+        let strip_server = NativeServer::from_layout(&strip, true, false).unwrap();
+        let counts: Vec<usize> = strip_server.cells().map(|c| c.surr_mine_count).collect();
+        assert_eq!(counts, vec![0, 1, 0, 2, 0]);
+    }
+
+    #[test]
+    fn test_layout_errors() {
+        assert!(Layout::parse("").is_err());
+        assert!(Layout::parse("*.\n*").is_err());
+        assert!(Layout::parse("*.\n.x").is_err());
+        assert!(Layout::parse("*.\n..\n\n**").is_err());
+    }
+}
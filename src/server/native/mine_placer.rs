@@ -0,0 +1,307 @@
+use rand::Rng;
+
+use crate::coords::Coords;
+use crate::GameError;
+
+/// Strategy for laying out mines on a new board. The layout must depend only on the parameters
+/// and the state of `rng`, so that a board can be reproduced from its seed.
+pub trait MinePlacer {
+    fn place<R: Rng>(
+        &self,
+        dims: &[usize],
+        mines: usize,
+        rng: &mut R,
+    ) -> Result<Vec<bool>, GameError>;
+}
+
+/// Uniformly random placement; matches the JS server's layout for a given seed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UniformPlacer;
+
+impl MinePlacer for UniformPlacer {
+    fn place<R: Rng>(
+        &self,
+        dims: &[usize],
+        mines: usize,
+        rng: &mut R,
+    ) -> Result<Vec<bool>, GameError> {
+        let size = dims.iter().product();
+
+        // Place mines randomly using Fisher-Yates shuffle
+        let mut mine_arr = vec![false; size];
+
+        for i in 0..size {
+            // Avoid rng-generation on first iteration to match the JS
+            // server's behaviour
+            let rand = if i == 0 {
+                0
+            } else {
+                rng.gen_range(0, (i + 1) as i32) as usize
+            };
+
+            if rand != i {
+                mine_arr[i] = mine_arr[rand];
+            }
+
+            mine_arr[rand] = i < mines;
+        }
+
+        Ok(mine_arr)
+    }
+}
+
+/// Mines concentrated around `clusters` randomly-chosen centres. Likelihood of a mine falls off
+/// with distance from the nearest centres, as a normal distribution with standard deviation
+/// `spread` cells.
+#[derive(Clone, Copy, Debug)]
+pub struct ClusteredPlacer {
+    pub clusters: usize,
+    pub spread: f64,
+}
+
+impl MinePlacer for ClusteredPlacer {
+    fn place<R: Rng>(
+        &self,
+        dims: &[usize],
+        mines: usize,
+        rng: &mut R,
+    ) -> Result<Vec<bool>, GameError> {
+        if self.clusters == 0 || self.spread.is_nan() || self.spread <= 0.0 {
+            return Err(format!("Invalid cluster params: {:?}", self))?;
+        }
+
+        let size = dims.iter().product();
+        let centres: Vec<Coords> = (0..self.clusters)
+            .map(|_| Coords::from_index(rng.gen_range(0, size), dims))
+            .collect();
+
+        let weights: Vec<f64> = (0..size)
+            .map(|i| {
+                let Coords(coords): Coords = Coords::from_index(i, dims);
+
+                centres
+                    .iter()
+                    .map(|Coords(centre)| {
+                        let dist_sq: f64 = coords
+                            .iter()
+                            .zip(centre.iter())
+                            .map(|(&a, &b)| (a as f64 - b as f64).powi(2))
+                            .sum();
+
+                        (-dist_sq / (2.0 * self.spread * self.spread)).exp()
+                    })
+                    .sum()
+            })
+            .collect();
+
+        Ok(place_weighted(&weights, mines, rng))
+    }
+}
+
+/// Mine density varying linearly along dimension `dim`, from relative weight `start` at the
+/// first co-ordinate to `end` at the last.
+#[derive(Clone, Copy, Debug)]
+pub struct GradientPlacer {
+    pub dim: usize,
+    pub start: f64,
+    pub end: f64,
+}
+
+impl MinePlacer for GradientPlacer {
+    fn place<R: Rng>(
+        &self,
+        dims: &[usize],
+        mines: usize,
+        rng: &mut R,
+    ) -> Result<Vec<bool>, GameError> {
+        let valid_weight = |w: f64| w.is_finite() && w >= 0.0;
+
+        if self.dim >= dims.len() || !valid_weight(self.start) || !valid_weight(self.end) {
+            return Err(format!(
+                "Invalid gradient params for dims={:?}: {:?}",
+                dims, self
+            ))?;
+        }
+
+        let size = dims.iter().product();
+        let steps = (dims[self.dim] - 1).max(1) as f64;
+
+        let weights: Vec<f64> = (0..size)
+            .map(|i| {
+                let Coords(coords): Coords = Coords::from_index(i, dims);
+                let t = coords[self.dim] as f64 / steps;
+
+                self.start + (self.end - self.start) * t
+            })
+            .collect();
+
+        Ok(place_weighted(&weights, mines, rng))
+    }
+}
+
+/// A fixed mine layout, given as a mine flag per cell index. Ignores the rng.
+#[derive(Clone, Debug)]
+pub struct ExplicitPlacer(pub Vec<bool>);
+
+impl MinePlacer for ExplicitPlacer {
+    fn place<R: Rng>(
+        &self,
+        dims: &[usize],
+        mines: usize,
+        _rng: &mut R,
+    ) -> Result<Vec<bool>, GameError> {
+        let size: usize = dims.iter().product();
+        let layout_mines = self.0.iter().filter(|&&m| m).count();
+
+        if self.0.len() != size || layout_mines != mines {
+            return Err(format!(
+                "Layout of {} cells/{} mines doesn't match dims={:?} mines={}",
+                self.0.len(),
+                layout_mines,
+                dims,
+                mines
+            ))?;
+        }
+
+        Ok(self.0.clone())
+    }
+}
+
+// Weighted random sampling of `mines` cells without replacement (Efraimidis-Spirakis). Cells of
+// zero weight are only chosen once all others have been. Weights must be finite and
+// non-negative.
+fn place_weighted<R: Rng>(weights: &[f64], mines: usize, rng: &mut R) -> Vec<bool> {
+    let mut keys: Vec<(f64, usize)> = weights
+        .iter()
+        .enumerate()
+        .map(|(i, &w)| (rng.gen::<f64>().ln() / w, i))
+        .collect();
+
+    // Keys are never NaN for valid weights: a zero weight or a zero sample gives -inf
+    keys.sort_by(|a, b| {
+        b.0.partial_cmp(&a.0)
+            .expect("Mine placement weights should be finite and non-negative")
+    });
+
+    let mut mine_arr = vec![false; weights.len()];
+
+    for &(_, i) in keys.iter().take(mines) {
+        mine_arr[i] = true;
+    }
+
+    mine_arr
+}
+
+#[cfg(test)]
+mod test {
+    use mersenne_twister::MT19937;
+    use rand::SeedableRng;
+    use std::f64;
+
+    use crate::server::native::{
+        ClusteredPlacer, ExplicitPlacer, GradientPlacer, MinePlacer, UniformPlacer,
+    };
+
+    fn place(placer: &impl MinePlacer, dims: &[usize], mines: usize, seed: u32) -> Vec<bool> {
+        let mut rng: MT19937 = SeedableRng::from_seed(seed);
+        placer.place(dims, mines, &mut rng).unwrap()
+    }
+
+    #[test]
+    fn test_placers_deterministic() {
+        let dims = [12, 10];
+        let gradient = GradientPlacer {
+            dim: 0,
+            start: 0.5,
+            end: 2.0,
+        };
+        let clustered = ClusteredPlacer {
+            clusters: 3,
+            spread: 2.0,
+        };
+
+        for seed in 0..5 {
+            let layouts = [
+                place(&UniformPlacer, &dims, 30, seed),
+                place(&gradient, &dims, 30, seed),
+                place(&clustered, &dims, 30, seed),
+            ];
+
+            for layout in layouts.iter() {
+                assert_eq!(layout.iter().filter(|&&m| m).count(), 30);
+            }
+
+            assert_eq!(layouts[0], place(&UniformPlacer, &dims, 30, seed));
+            assert_eq!(layouts[1], place(&gradient, &dims, 30, seed));
+            assert_eq!(layouts[2], place(&clustered, &dims, 30, seed));
+        }
+    }
+
+    #[test]
+    fn test_gradient_distribution() {
+        // Columns along the first dimension, with weights rising from 0 to 1
+        let dims = [10, 10];
+        let gradient = GradientPlacer {
+            dim: 0,
+            start: 0.0,
+            end: 1.0,
+        };
+        let mut column_mines = [0; 10];
+
+        for seed in 0..50 {
+            for (i, &mine) in place(&gradient, &dims, 20, seed).iter().enumerate() {
+                if mine {
+                    column_mines[i / 10] += 1;
+                }
+            }
+        }
+
+        // Zero-weight cells are only used once every other cell has a mine
+        assert_eq!(column_mines[0], 0);
+
+        let low: usize = column_mines[1..5].iter().sum();
+        let high: usize = column_mines[5..].iter().sum();
+        assert!(high > low * 2, "{:?}", column_mines);
+
+        // All cells are mines, including zero-weight ones
+        assert!(place(&gradient, &dims, 100, 1).iter().all(|&m| m));
+    }
+
+    #[test]
+    fn test_placer_errors() {
+        let mut rng: MT19937 = SeedableRng::from_seed(1);
+        let dims = [5, 5];
+        let gradient = |dim, start, end| GradientPlacer { dim, start, end };
+
+        for invalid in [
+            gradient(2, 1.0, 1.0),
+            gradient(0, -1.0, 1.0),
+            gradient(0, f64::NAN, 1.0),
+            gradient(0, 1.0, f64::INFINITY),
+        ]
+        .iter()
+        {
+            assert!(invalid.place(&dims, 5, &mut rng).is_err());
+        }
+
+        for invalid in [
+            ClusteredPlacer {
+                clusters: 0,
+                spread: 1.0,
+            },
+            ClusteredPlacer {
+                clusters: 1,
+                spread: f64::NAN,
+            },
+        ]
+        .iter()
+        {
+            assert!(invalid.place(&dims, 5, &mut rng).is_err());
+        }
+
+        let explicit = ExplicitPlacer(vec![true, false, false, false]);
+        assert!(explicit.place(&[2, 2], 1, &mut rng).is_ok());
+        assert!(explicit.place(&[2, 2], 2, &mut rng).is_err());
+        assert!(explicit.place(&[3, 2], 1, &mut rng).is_err());
+    }
+}
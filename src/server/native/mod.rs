@@ -1,15 +1,21 @@
+mod layout;
+mod mine_placer;
+
 #[cfg(feature = "chrono")]
 use chrono::{DateTime, Utc};
-use itertools::Itertools;
-use mersenne_twister::MT19937;
-use rand::{Rng, SeedableRng};
-use std::collections::HashSet;
 
 use crate::coords::Coords;
-use crate::game_grid::GameGrid;
+use crate::game_grid::{Neighbourhood, Neighbours};
+use crate::render::TextRenderer;
 use crate::server::{CellInfo, GameServer, GameSpec, GameState};
+use crate::util::BitVec;
 use crate::GameError;
 
+pub use self::layout::Layout;
+pub use self::mine_placer::{
+    ClusteredPlacer, ExplicitPlacer, GradientPlacer, MinePlacer, UniformPlacer,
+};
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum CellAction {
     NoAction,
@@ -17,20 +23,30 @@ pub enum CellAction {
     Cleared,
 }
 
-#[derive(Debug)]
+/// State of a single cell, as returned by `NativeServer::cell`.
+#[derive(Clone, Copy, Debug)]
 pub struct Cell {
     pub mine: bool,
     pub action: CellAction,
-    pub surr_indices: HashSet<usize>,
     pub surr_mine_count: usize,
 }
 
-impl Cell {
-    fn set_action(&mut self, new_action: CellAction) -> Result<(), ()> {
-        if self.action == new_action || self.action == CellAction::Cleared {
+// Cell state as flat arrays indexed by cell index, so that clearing large openings touches as
+// little memory as possible.
+struct CellStore {
+    mines: BitVec,
+    actions: Vec<CellAction>,
+    surr_mine_counts: Vec<u32>,
+}
+
+impl CellStore {
+    fn set_action(&mut self, index: usize, new_action: CellAction) -> Result<(), ()> {
+        let action = &mut self.actions[index];
+
+        if *action == new_action || *action == CellAction::Cleared {
             Err(())
         } else {
-            self.action = new_action;
+            *action = new_action;
             Ok(())
         }
     }
@@ -40,7 +56,8 @@ pub struct NativeServer {
     #[cfg(feature = "chrono")]
     pub created_at: DateTime<Utc>,
     pub dims: Vec<usize>,
-    pub grid: GameGrid<Cell>,
+    neighbourhood: Neighbourhood,
+    cells: CellStore,
     pub mines: usize,
     pub seed: u32,
     pub autoclear: bool,
@@ -53,6 +70,8 @@ pub struct NativeServer {
 pub struct TurnInfo {
     #[cfg(feature = "chrono")]
     pub timestamp: DateTime<Utc>,
+    /// Name of the client which took the turn, if known.
+    pub client: Option<String>,
     pub clear_req: Vec<usize>,
     pub clear_actual: Vec<usize>,
     pub flagged: Vec<usize>,
@@ -62,14 +81,20 @@ pub struct TurnInfo {
 }
 
 impl NativeServer {
-    pub fn new(
+    pub fn new(spec: GameSpec, store_turns: bool) -> Result<Self, GameError> {
+        Self::with_placer(spec, store_turns, &UniformPlacer)
+    }
+
+    pub fn with_placer(
         GameSpec {
             dims,
             mines,
             seed,
             autoclear,
+            rng,
         }: GameSpec,
         store_turns: bool,
+        placer: &impl MinePlacer,
     ) -> Result<Self, GameError> {
         let size = dims.iter().fold(1, |s, &i| s * i);
         let cells_rem = size - mines;
@@ -82,44 +107,29 @@ impl NativeServer {
             );
         }
 
-        let grid: GameGrid<Cell> = {
-            let mut rng: MT19937 = SeedableRng::from_seed(seed);
+        let mine_arr = rng.place(placer, &dims, mines, seed)?;
 
-            // Place mines randomly using Fisher-Yates shuffle
-            let mut mine_arr = vec![false; size];
+        let neighbourhood = Neighbourhood::new(&dims);
+        let mine_bits = BitVec::from(mine_arr.as_slice());
+        let mut surr_mine_counts = vec![0; size];
 
-            for i in 0..size {
-                // Avoid rng-generation on first iteration to match the JS
-                // server's behaviour
-                let rand = if i == 0 {
-                    0
-                } else {
-                    rng.gen_range(0, (i + 1) as i32) as usize
-                };
-
-                if rand != i {
-                    mine_arr[i] = mine_arr[rand];
-                }
-
-                mine_arr[rand] = i < mines;
+        for index in mine_bits.ones() {
+            for surr in neighbourhood.neighbours(index).iter() {
+                surr_mine_counts[surr] += 1;
             }
+        }
 
-            GameGrid::new(&dims, |i, surr| {
-                let surr_mine_count = surr.iter().filter(|&&s| mine_arr[s]).count();
-
-                Cell {
-                    mine: mine_arr[i],
-                    action: CellAction::NoAction,
-                    surr_indices: surr,
-                    surr_mine_count,
-                }
-            })
+        let cells = CellStore {
+            mines: mine_bits,
+            actions: vec![CellAction::NoAction; size],
+            surr_mine_counts,
         };
 
         let turns = if store_turns {
             Some(vec![TurnInfo {
                 #[cfg(feature = "chrono")]
                 timestamp: Utc::now(),
+                client: None,
                 clear_req: Vec::new(),
                 clear_actual: Vec::new(),
                 flagged: Vec::new(),
@@ -138,73 +148,129 @@ impl NativeServer {
             mines,
             seed,
             autoclear,
-            grid,
+            neighbourhood,
+            cells,
             cells_rem,
             game_state,
             turns,
         })
     }
 
-    #[allow(dead_code)]
-    pub fn grid_repr(&self) -> Result<String, String> {
-        if self.dims.len() > 2 {
-            return Err(format!(
-                "Can only repr game of <= 2 dimensions; dims={:?}",
-                self.dims
-            ));
+    /// The board as text, as rendered by a default `TextRenderer`, for any number of
+    /// dimensions.
+    pub fn grid_repr(&self) -> String {
+        TextRenderer::default().render(self)
+    }
+
+    pub fn cell(&self, index: usize) -> Cell {
+        Cell {
+            mine: self.cells.mines.get(index),
+            action: self.cells.actions[index],
+            surr_mine_count: self.cells.surr_mine_counts[index] as usize,
         }
+    }
 
-        let cell_repr = |x, y| {
-            let index = Coords(vec![x, y]).to_index(&self.dims);
-            let cell = &self.grid[index];
-            match cell.action {
-                CellAction::NoAction => '□',
-                CellAction::Flagged => '⚐',
-                CellAction::Cleared => {
-                    if cell.mine {
-                        '☢'
-                    } else {
-                        match cell.surr_mine_count {
-                            0 => ' ',
-                            c => (c as u8 + '0' as u8) as char,
-                        }
-                    }
-                }
-            }
+    /// All cells, in index order.
+    pub fn cells<'a>(&'a self) -> impl Iterator<Item = Cell> + 'a {
+        (0..self.cells.actions.len()).map(move |index| self.cell(index))
+    }
+
+    pub fn neighbours(&self, index: usize) -> Neighbours<'_> {
+        self.neighbourhood.neighbours(index)
+    }
+
+    /// Takes a turn on behalf of a named client, which is recorded in the turn's info.
+    pub fn client_turn(
+        &mut self,
+        client: Option<String>,
+        clear: Vec<Coords>,
+        flag: Vec<Coords>,
+        unflag: Vec<Coords>,
+    ) -> Result<Vec<CellInfo>, GameError> {
+        if self.game_state != GameState::Ongoing {
+            return Err(String::from("Game already finished"))?;
+        }
+
+        let to_indices = |coords: Vec<Coords>| -> Result<Vec<usize>, GameError> {
+            coords.iter().map(|c| c.try_to_index(&self.dims)).collect()
         };
 
-        let row_repr = |y| (0..self.dims[0]).map(|x| cell_repr(x, y)).join(" ");
+        // Validate all co-ordinates before making any changes
+        let clear_req_indices = to_indices(clear)?;
+        let flag_indices = to_indices(flag)?;
+        let unflag_indices = to_indices(unflag)?;
+
+        let clear_actual = self.clear_cells(clear_req_indices.clone());
+        let flag_actual = self.set_flags(flag_indices, CellAction::Flagged);
+        let unflag_actual = self.set_flags(unflag_indices, CellAction::NoAction);
 
-        let row_count = *self.dims.get(1).unwrap_or(&1);
+        if let Some(ref mut turns) = self.turns {
+            let turn_info = TurnInfo {
+                #[cfg(feature = "chrono")]
+                timestamp: Utc::now(),
+                client,
+                clear_req: clear_req_indices,
+                clear_actual: clear_actual.clone(),
+                flagged: flag_actual,
+                unflagged: unflag_actual,
+                cells_rem: self.cells_rem,
+                game_state: self.game_state,
+            };
 
-        Ok((0..row_count).map(row_repr).join("\n"))
+            turns.push(turn_info);
+        }
+
+        let client_cell_info = clear_actual
+            .iter()
+            .map(|&index| self.client_cell_info(index))
+            .collect();
+
+        Ok(client_cell_info)
     }
 
-    fn clear_cells(&mut self, mut to_clear: Vec<usize>) -> Vec<usize> {
-        let mut clear_actual = Vec::new();
+    fn clear_cells(&mut self, to_clear: Vec<usize>) -> Vec<usize> {
+        let NativeServer {
+            ref neighbourhood,
+            ref mut cells,
+            ref mut cells_rem,
+            ref mut game_state,
+            autoclear,
+            ..
+        } = *self;
 
-        while let Some(index) = to_clear.pop() {
-            let cell = &mut self.grid[index];
+        let mut clear_actual = Vec::new();
 
-            if let Err(_) = cell.set_action(CellAction::Cleared) {
-                continue;
+        // Clears a cell if not already cleared; returns whether its neighbours should be
+        // cleared too.
+        let mut clear = |index: usize| {
+            if cells.set_action(index, CellAction::Cleared).is_err() {
+                return false;
             }
 
             clear_actual.push(index);
 
-            if cell.mine {
-                self.game_state = GameState::Lose;
-            } else {
-                if self.autoclear && cell.surr_mine_count == 0 {
-                    for &i in cell.surr_indices.iter() {
-                        to_clear.push(i);
-                    }
-                }
+            if cells.mines.get(index) {
+                *game_state = GameState::Lose;
+                return false;
+            }
+
+            *cells_rem -= 1;
+
+            if *cells_rem == 0 {
+                *game_state = GameState::Win;
+            }
 
-                self.cells_rem -= 1;
+            autoclear && cells.surr_mine_counts[index] == 0
+        };
+
+        // Cells are cleared as soon as they're reached, so each cell of an opening is only
+        // queued once, however many empty neighbours it has.
+        let mut to_cascade: Vec<usize> = to_clear.into_iter().filter(|&i| clear(i)).collect();
 
-                if self.cells_rem == 0 {
-                    self.game_state = GameState::Win;
+        while let Some(index) = to_cascade.pop() {
+            for surr in neighbourhood.neighbours(index).iter() {
+                if clear(surr) {
+                    to_cascade.push(surr);
                 }
             }
         }
@@ -212,14 +278,11 @@ impl NativeServer {
         clear_actual
     }
 
-    fn set_flags(&mut self, to_change: Vec<Coords>, new_action: CellAction) -> Vec<usize> {
+    fn set_flags(&mut self, to_change: Vec<usize>, new_action: CellAction) -> Vec<usize> {
         let mut actual_change = Vec::new();
 
-        for coords in to_change.into_iter() {
-            let index = coords.to_index(&self.dims);
-            let cell = &mut self.grid[index];
-
-            if let Ok(_) = cell.set_action(new_action) {
+        for index in to_change.into_iter() {
+            if let Ok(_) = self.cells.set_action(index, new_action) {
                 actual_change.push(index);
             }
         }
@@ -228,7 +291,7 @@ impl NativeServer {
     }
 
     fn client_cell_info(&self, index: usize) -> CellInfo {
-        let cell = &self.grid[index];
+        let cell = self.cell(index);
 
         CellInfo {
             coords: Coords::from_index(index, &self.dims),
@@ -245,44 +308,15 @@ impl GameServer for NativeServer {
         flag: Vec<Coords>,
         unflag: Vec<Coords>,
     ) -> Result<Vec<CellInfo>, GameError> {
-        if self.game_state != GameState::Ongoing {
-            return Err(String::from("Game already finished"))?;
-        }
-
-        let clear_req_indices: Vec<usize> = clear
-            .iter()
-            .map(|coords| coords.to_index(&self.dims))
-            .collect();
-
-        let clear_actual = self.clear_cells(clear_req_indices.clone());
-        let flag_actual = self.set_flags(flag, CellAction::Flagged);
-        let unflag_actual = self.set_flags(unflag, CellAction::NoAction);
-
-        if let Some(ref mut turns) = self.turns {
-            let turn_info = TurnInfo {
-                #[cfg(feature = "chrono")]
-                timestamp: Utc::now(),
-                clear_req: clear_req_indices,
-                clear_actual: clear_actual.clone(),
-                flagged: flag_actual,
-                unflagged: unflag_actual,
-                cells_rem: self.cells_rem,
-                game_state: self.game_state,
-            };
-
-            turns.push(turn_info);
-        }
-
-        let client_cell_info = clear_actual
-            .iter()
-            .map(|&index| self.client_cell_info(index))
-            .collect();
+        self.client_turn(None, clear, flag, unflag)
+    }
 
-        Ok(client_cell_info)
+    fn dims(&self) -> Option<&[usize]> {
+        Some(&self.dims)
     }
 
-    fn dims(&self) -> &[usize] {
-        &self.dims
+    fn dim_count(&self) -> usize {
+        self.dims.len()
     }
 
     fn mines(&self) -> usize {
@@ -297,3 +331,138 @@ impl GameServer for NativeServer {
         self.cells_rem
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::coords::Coords;
+    use crate::server::native::NativeServer;
+    use crate::server::{GameServer, GameSpec, GameState};
+
+    // Plays a fixed sequence of clears and flags, and returns the number of turns taken and a
+    // checksum of every turn's cleared cells and cells remaining.
+    fn play_checksum(dims: &[usize], mines: usize, autoclear: bool, seed: u32) -> (usize, u64) {
+        let spec = GameSpec {
+            dims: dims.to_vec(),
+            mines,
+            seed,
+            autoclear,
+            rng: Default::default(),
+        };
+        let mut server = NativeServer::new(spec, false).unwrap();
+        let size: usize = dims.iter().product();
+        let mut checksum: u64 = 0;
+        let mut turns = 0;
+        let mut add = |x: u64| checksum = checksum.wrapping_mul(1_000_003).wrapping_add(x);
+
+        for k in 0..40 {
+            let coords = Coords::from_index((k * 7919 + seed as usize * 31) % size, dims);
+            let (clear, flag) = if k % 5 == 4 {
+                (vec![], vec![coords])
+            } else {
+                (vec![coords], vec![])
+            };
+
+            // Cells are cleared in a different order to the old grid, so compare them sorted
+            let mut cleared: Vec<usize> = server
+                .turn(clear, flag, vec![])
+                .unwrap()
+                .iter()
+                .map(|cell| cell.coords.to_index(dims))
+                .collect();
+            cleared.sort();
+
+            turns += 1;
+
+            for index in cleared {
+                add(index as u64);
+            }
+
+            add(server.cells_rem as u64 + 1_000_000);
+
+            if server.game_state != GameState::Ongoing {
+                break;
+            }
+        }
+
+        (turns, checksum)
+    }
+
+    // Dims, mines and autoclear, and the turns and checksum for each of four seeds
+    type CascadeCase = (&'static [usize], usize, bool, [(usize, u64); 4]);
+
+    #[test]
+    fn test_cascade_regression() {
+        // Recorded from the server before cells were stored as flat arrays
+        let expected: &[CascadeCase] = &[
+            (
+                &[16, 16],
+                20,
+                true,
+                [
+                    (3, 6874404950022104789),
+                    (2, 13553775928561451613),
+                    (6, 14575930883794249863),
+                    (23, 2535485092306006976),
+                ],
+            ),
+            (
+                &[16, 16],
+                40,
+                false,
+                [
+                    (3, 13531574615821536690),
+                    (2, 13553755928441451413),
+                    (6, 5976433955896819991),
+                    (23, 4775457073623132491),
+                ],
+            ),
+            (
+                &[6, 6, 6],
+                15,
+                true,
+                [
+                    (13, 17275663553393842170),
+                    (36, 2439325903224727768),
+                    (3, 12064364250337701165),
+                    (13, 11577939668604745804),
+                ],
+            ),
+            (
+                &[40],
+                4,
+                true,
+                [
+                    (6, 2999684477197521481),
+                    (8, 16725245217561267308),
+                    (1, 23000102),
+                    (1, 14000075),
+                ],
+            ),
+            (
+                &[4, 4, 4, 4],
+                6,
+                true,
+                [
+                    (14, 12632023533485635379),
+                    (40, 14417891675561484996),
+                    (6, 4476706521091648312),
+                    (23, 16637530544478351645),
+                ],
+            ),
+        ];
+
+        for &(dims, mines, autoclear, games) in expected.iter() {
+            for (seed, &game) in games.iter().enumerate() {
+                assert_eq!(
+                    play_checksum(dims, mines, autoclear, seed as u32),
+                    game,
+                    "dims={:?} mines={} autoclear={} seed={}",
+                    dims,
+                    mines,
+                    autoclear,
+                    seed
+                );
+            }
+        }
+    }
+}
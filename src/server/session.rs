@@ -0,0 +1,175 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::coords::Coords;
+use crate::server::native::NativeServer;
+use crate::server::{CellInfo, GameServer, GameState};
+use crate::GameError;
+
+struct SessionState {
+    server: NativeServer,
+    // Every cell cleared so far, in the order they were cleared
+    cleared: Vec<Coords>,
+}
+
+/// A single game shared between several named clients, which may take turns from different
+/// threads. Each client plays through its own `SessionClient` handle.
+#[derive(Clone)]
+pub struct Session {
+    state: Arc<Mutex<SessionState>>,
+    dims: Vec<usize>,
+}
+
+impl Session {
+    pub fn new(server: NativeServer) -> Self {
+        let dims = server.dims.clone();
+
+        Session {
+            state: Arc::new(Mutex::new(SessionState {
+                server,
+                cleared: Vec::new(),
+            })),
+            dims,
+        }
+    }
+
+    pub fn client(&self, name: impl Into<String>) -> SessionClient {
+        SessionClient {
+            name: name.into(),
+            session: self.clone(),
+            cleared_seen: 0,
+        }
+    }
+
+    pub fn with_server<T>(&self, f: impl FnOnce(&NativeServer) -> T) -> T {
+        f(&self.lock().server)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SessionState> {
+        self.state.lock().expect("Session lock poisoned")
+    }
+}
+
+/// A named client's view of a `Session`. As well as the cells cleared by its own turn, each turn
+/// reports the cells which other clients have cleared since this client's previous turn.
+pub struct SessionClient {
+    name: String,
+    session: Session,
+    // Number of the session's cleared cells already reported to this client
+    cleared_seen: usize,
+}
+
+impl SessionClient {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl GameServer for SessionClient {
+    fn turn(
+        &mut self,
+        clear: Vec<Coords>,
+        flag: Vec<Coords>,
+        unflag: Vec<Coords>,
+    ) -> Result<Vec<CellInfo>, GameError> {
+        let mut state = self.session.lock();
+        let SessionState {
+            ref mut server,
+            ref mut cleared,
+        } = *state;
+
+        // Another client may have finished the game since this client's last turn
+        if server.game_state == GameState::Ongoing {
+            let clear_actual = server.client_turn(Some(self.name.clone()), clear, flag, unflag)?;
+            cleared.extend(clear_actual.into_iter().map(|info| info.coords));
+        }
+
+        let cell_info = cleared[self.cleared_seen..]
+            .iter()
+            .map(|coords| {
+                let cell = server.cell(coords.to_index(&server.dims));
+
+                CellInfo {
+                    coords: coords.clone(),
+                    mine: cell.mine,
+                    surrounding: cell.surr_mine_count,
+                }
+            })
+            .collect();
+
+        self.cleared_seen = cleared.len();
+
+        Ok(cell_info)
+    }
+
+    fn dims(&self) -> Option<&[usize]> {
+        Some(&self.session.dims)
+    }
+
+    fn dim_count(&self) -> usize {
+        self.session.dims.len()
+    }
+
+    fn mines(&self) -> usize {
+        self.session.lock().server.mines
+    }
+
+    fn game_state(&self) -> GameState {
+        self.session.lock().server.game_state
+    }
+
+    fn cells_rem(&self) -> usize {
+        self.session.lock().server.cells_rem
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::client::Client;
+    use crate::server::native::NativeServer;
+    use crate::server::session::Session;
+    use crate::server::{BoardRng, GameSpec, GameState};
+
+    #[test]
+    fn test_session_turns() {
+        let spec = GameSpec {
+            dims: vec![16, 16],
+            mines: 20,
+            seed: 4,
+            autoclear: true,
+            rng: BoardRng::default(),
+        };
+        let session = Session::new(NativeServer::new(spec, true).unwrap());
+
+        let mut alice = session.client("alice");
+        let mut bob = session.client("bob");
+        let mut alice_client = Client::new(&mut alice);
+        let mut bob_client = Client::new(&mut bob);
+
+        let mut alice_playing = true;
+        let mut bob_playing = true;
+
+        while alice_playing || bob_playing {
+            if alice_playing {
+                alice_playing = alice_client.turn().unwrap();
+            }
+
+            if bob_playing {
+                bob_playing = bob_client.turn().unwrap();
+            }
+        }
+
+        session.with_server(|server| {
+            assert_ne!(server.game_state, GameState::Ongoing);
+
+            let turns = server.turns.as_ref().unwrap();
+            let names: Vec<Option<&str>> = turns
+                .iter()
+                .map(|t| t.client.as_ref().map(String::as_str))
+                .collect();
+
+            assert_eq!(names[0], None);
+            assert!(names.contains(&Some("alice")));
+            assert!(names.contains(&Some("bob")));
+        });
+    }
+}
@@ -19,3 +19,58 @@ pub fn index_pair<T>(slice: &mut [T], ia: usize, ib: usize) -> (&mut T, &mut T)
 
     (a, b)
 }
+
+// Fixed-length vector of bools, packed into 64-bit blocks.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BitVec {
+    blocks: Vec<u64>,
+    len: usize,
+}
+
+impl BitVec {
+    pub fn new(len: usize) -> Self {
+        BitVec {
+            blocks: vec![0; len / 64 + 1],
+            len,
+        }
+    }
+
+    pub fn get(&self, index: usize) -> bool {
+        assert!(index < self.len, "BitVec index {} >= {}", index, self.len);
+        self.blocks[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    pub fn set(&mut self, index: usize, value: bool) {
+        assert!(index < self.len, "BitVec index {} >= {}", index, self.len);
+        let bit = 1 << (index % 64);
+
+        if value {
+            self.blocks[index / 64] |= bit;
+        } else {
+            self.blocks[index / 64] &= !bit;
+        }
+    }
+
+    // Indices of all set bits, in ascending order.
+    pub fn ones<'a>(&'a self) -> impl Iterator<Item = usize> + 'a {
+        self.blocks.iter().enumerate().flat_map(|(b, &block)| {
+            (0..64)
+                .filter(move |i| block & (1 << i) != 0)
+                .map(move |i| b * 64 + i)
+        })
+    }
+}
+
+impl<'a> From<&'a [bool]> for BitVec {
+    fn from(bools: &'a [bool]) -> Self {
+        let mut bits = BitVec::new(bools.len());
+
+        for (i, &b) in bools.iter().enumerate() {
+            if b {
+                bits.set(i, true);
+            }
+        }
+
+        bits
+    }
+}
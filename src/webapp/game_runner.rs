@@ -1,5 +1,9 @@
-use mines_rs::{GameBatch, NativeServer, SpecResult};
+use mines_rs::{
+    BatchConfig, BatchProgress, CancelToken, GameBatch, MinesSpec, NativeServer, Shard, ShardBy,
+    SpecResult,
+};
 use serde_derive::{Deserialize, Serialize};
+use stdweb::web::set_timeout;
 use yew::agent::{Agent, AgentLink, HandlerId, Transferable};
 
 mod agent_link_type {
@@ -14,35 +18,174 @@ mod agent_link_type {
 
 pub struct GameBatchRunner {
     link: AgentLink<GameBatchRunner>,
+    cancel: CancelToken,
+    run: Option<BatchRun>,
+    next_run_id: usize,
+}
+
+// A batch played one spec at a time, yielding to the browser between specs so that `Cancel`
+// messages are handled while it runs. Each spec is a shard of its own, so the games are the
+// same as for an uninterrupted run.
+struct BatchRun {
+    id: usize,
+    who: HandlerId,
+    config: BatchConfig,
+    spec_count: usize,
+    next_spec: usize,
+    // Games played in the specs before `next_spec`, and in the whole batch
+    played: usize,
+    total: usize,
+    results: Vec<SpecResult<()>>,
+}
+
+impl BatchRun {
+    // Plays the next spec; returns whether the run is over.
+    fn step(&mut self, link: &AgentLink<GameBatchRunner>, cancel: &CancelToken) -> bool {
+        let (who, played, total) = (self.who, self.played, self.total);
+        let shard = Shard {
+            index: self.next_spec,
+            count: self.spec_count,
+            by: ShardBy::Spec,
+        };
+
+        let shard_result = self
+            .config
+            .clone()
+            .run_shard(
+                shard,
+                |spec| NativeServer::new(spec, false),
+                |_game| (),
+                cancel,
+                |_game_result, progress| {
+                    if progress.spec_played == progress.spec_total {
+                        // Counted here for the whole batch, rather than for the one-spec shard
+                        let progress = BatchProgress {
+                            played: played + progress.spec_played,
+                            total,
+                            ..progress
+                        };
+
+                        link.response(who, GameBatchResultMessage::Progress(progress));
+                    }
+                },
+            )
+            .unwrap();
+
+        self.played += shard_result
+            .specs
+            .iter()
+            .map(|shard_spec| shard_spec.result.played)
+            .sum::<usize>();
+        self.results.extend(
+            shard_result
+                .specs
+                .into_iter()
+                .map(|shard_spec| shard_spec.result),
+        );
+        self.next_spec += 1;
+
+        self.next_spec == self.spec_count || cancel.is_cancelled()
+    }
 }
 
 #[derive(Serialize, Deserialize)]
-pub struct GameBatchMessage(pub GameBatch<Vec<usize>, Vec<usize>>);
+pub enum GameBatchMessage {
+    Run(GameBatch<Vec<usize>, Vec<MinesSpec>>),
+    /// Stops the running batch after its current spec, which then responds with the results
+    /// so far.
+    Cancel,
+}
 
 #[derive(Serialize, Deserialize)]
-pub struct GameBatchResultMessage(pub Vec<SpecResult<()>>);
+pub enum GameBatchResultMessage {
+    /// Sent as each spec is finished.
+    Progress(BatchProgress),
+    Done(Vec<SpecResult<()>>),
+}
 
 impl Transferable for GameBatchMessage {}
 impl Transferable for GameBatchResultMessage {}
 
+impl GameBatchRunner {
+    // Plays the run's next spec once the browser has handled any waiting events
+    fn schedule(&self, run_id: usize) {
+        let next_spec = self.link.send_back(|run_id| run_id);
+
+        set_timeout(move || next_spec.emit(run_id), 0);
+    }
+
+    fn finish(&mut self) {
+        if let Some(run) = self.run.take() {
+            self.link
+                .response(run.who, GameBatchResultMessage::Done(run.results));
+        }
+    }
+}
+
 impl Agent for GameBatchRunner {
     type Reach = agent_link_type::Job;
-    type Message = ();
+    /// Id of the run whose next spec should be played.
+    type Message = usize;
     type Input = GameBatchMessage;
     type Output = GameBatchResultMessage;
 
     fn create(link: AgentLink<Self>) -> Self {
-        GameBatchRunner { link }
+        GameBatchRunner {
+            link,
+            cancel: CancelToken::new(),
+            run: None,
+            next_run_id: 0,
+        }
     }
 
-    fn update(&mut self, msg: Self::Message) {}
+    fn update(&mut self, run_id: Self::Message) {
+        // Steps scheduled for a replaced run are ignored
+        let finished = match self.run {
+            Some(ref mut run) if run.id == run_id => {
+                self.cancel.is_cancelled() || run.step(&self.link, &self.cancel)
+            }
+            _ => return,
+        };
 
-    fn handle(&mut self, batch: Self::Input, who: HandlerId) {
-        let results = batch
-            .0
-            .run(|spec| NativeServer::new(spec, false), |_game| ())
-            .unwrap();
+        if finished {
+            self.finish();
+        } else {
+            self.schedule(run_id);
+        }
+    }
+
+    fn handle(&mut self, msg: Self::Input, who: HandlerId) {
+        let batch = match msg {
+            GameBatchMessage::Run(batch) => batch,
+            GameBatchMessage::Cancel => {
+                self.cancel.cancel();
+                return;
+            }
+        };
+
+        let config = batch.into_config();
+        let specs = config.specs().unwrap();
+        let spec_count = specs.len();
+        let total = specs.iter().map(|spec| spec.count).sum();
+        let id = self.next_run_id;
+
+        self.next_run_id += 1;
+        self.cancel = CancelToken::new();
+        self.run = Some(BatchRun {
+            id,
+            who,
+            config,
+            spec_count,
+            next_spec: 0,
+            played: 0,
+            total,
+            results: Vec::new(),
+        });
 
-        self.link.response(who, GameBatchResultMessage(results));
+        if spec_count == 0 {
+            self.finish();
+        } else {
+            self.schedule(id);
+        }
     }
 }
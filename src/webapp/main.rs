@@ -3,7 +3,7 @@
 mod game_runner;
 
 use game_runner::{GameBatchMessage, GameBatchResultMessage, GameBatchRunner};
-use mines_rs::{GameBatch, NativeServer, SpecResult};
+use mines_rs::{BatchProgress, BoardRng, GameBatch, NativeServer, SpecResult};
 use std::iter::once;
 use stdweb::{_js_impl, js};
 use yew::agent::{Bridge, Bridged};
@@ -12,6 +12,8 @@ use yew::{html, html_impl};
 
 enum GameViewerMsg {
     DoBatch,
+    CancelBatch,
+    BatchProgress(BatchProgress),
     BatchResult(Vec<SpecResult<()>>),
 }
 
@@ -28,10 +30,11 @@ impl GameViewer {
             mines_range: (10..=50).step_by(5),
             autoclear: true,
             metaseed: 133337,
+            rng: BoardRng::default(),
         }
         .into_serializable();
 
-        self.game_runner.send(GameBatchMessage(batch));
+        self.game_runner.send(GameBatchMessage::Run(batch));
     }
 }
 
@@ -40,8 +43,10 @@ impl Component for GameViewer {
     type Properties = ();
 
     fn create(_props: Self::Properties, mut link: ComponentLink<Self>) -> Self {
-        let callback =
-            link.send_back(|GameBatchResultMessage(results)| GameViewerMsg::BatchResult(results));
+        let callback = link.send_back(|msg| match msg {
+            GameBatchResultMessage::Progress(progress) => GameViewerMsg::BatchProgress(progress),
+            GameBatchResultMessage::Done(results) => GameViewerMsg::BatchResult(results),
+        });
         let game_runner = GameBatchRunner::bridge(callback);
 
         Self { game_runner }
@@ -52,6 +57,13 @@ impl Component for GameViewer {
             GameViewerMsg::DoBatch => {
                 self.do_batch();
             }
+            GameViewerMsg::CancelBatch => {
+                self.game_runner.send(GameBatchMessage::Cancel);
+            }
+            GameViewerMsg::BatchProgress(progress) => {
+                let BatchProgress { played, total, .. } = progress;
+                js! { console.log(@{played as u32} + "/" + @{total as u32} + " games"); }
+            }
             GameViewerMsg::BatchResult(results) => {
                 js! { console.table(@{results}); }
             }
@@ -66,6 +78,7 @@ impl Renderable<GameViewer> for GameViewer {
         html! {
             <div class="gameArea",>
                 <button onclick=|_| GameViewerMsg::DoBatch,>{"clicky"}</button>
+                <button onclick=|_| GameViewerMsg::CancelBatch,>{"cancel"}</button>
             </div>
         }
     }
@@ -85,6 +98,7 @@ fn run_sample() {
         mines_range: (10..=50).step_by(5),
         autoclear: true,
         metaseed: 133337,
+        rng: BoardRng::default(),
     }
     .into_serializable();
 